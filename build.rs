@@ -17,12 +17,20 @@ fn main() {
     build_wasm_test_program("rust-http-test.wasm", "crates/http/tests/rust-http-test");
     build_wasm_test_program("redis-rust.wasm", "crates/redis/tests/rust");
     build_wasm_test_program("wagi-test.wasm", "crates/http/tests/wagi-test");
+    build_wasm_test_program(
+        "experimental-http-test.wasm",
+        "crates/http/tests/experimental-http-test",
+    );
 
     build_wasm_test_program(
         "spin-http-benchmark.wasm",
         "crates/http/benches/spin-http-benchmark",
     );
     build_wasm_test_program("wagi-benchmark.wasm", "crates/http/benches/wagi-benchmark");
+    build_wasm_test_program(
+        "outbound-http-benchmark.wasm",
+        "crates/http/benches/outbound-http-benchmark",
+    );
     build_wasm_test_program("echo.wasm", "examples/spin-timer/example");
 
     cargo_build(RUST_HTTP_INTEGRATION_TEST);
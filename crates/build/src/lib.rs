@@ -0,0 +1,137 @@
+//! A library for building Spin components.
+
+#![deny(missing_docs)]
+
+use anyhow::{anyhow, bail, Context, Result};
+use path_absolutize::Absolutize;
+use spin_loader::local::{
+    config::{RawAppManifestAnyVersion, RawComponentManifest},
+    raw_manifest_from_file,
+};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use tracing::log;
+
+/// If the component has a build configuration, run its build command.
+/// Components are built one at a time, in the order in which they appear in
+/// the manifest, unless `parallel` is set. The first build that fails stops
+/// the build of the application.
+///
+/// Returns the IDs of the components that were built, which is empty if no
+/// component has a build configuration.
+pub async fn build(manifest_file: &Path, parallel: bool) -> Result<Vec<String>> {
+    let manifest_file = manifest_file
+        .absolutize()
+        .context("Failed to resolve absolute path to manifest file")?;
+    let RawAppManifestAnyVersion::V1(app) = raw_manifest_from_file(&manifest_file).await?;
+    let app_dir = parent_dir(&manifest_file)?;
+
+    let components = app
+        .components
+        .iter()
+        .filter(|c| {
+            if c.build.is_none() {
+                log::debug!("No build command found for component {}, skipping", c.id);
+            }
+            c.build.is_some()
+        })
+        .collect::<Vec<_>>();
+
+    if parallel {
+        futures::future::try_join_all(components.iter().map(|c| build_component(c, &app_dir)))
+            .await?;
+    } else {
+        for c in components {
+            build_component(c, &app_dir).await?;
+        }
+    }
+
+    Ok(components.iter().map(|c| c.id.clone()).collect())
+}
+
+/// Run the build command of the component.
+async fn build_component(raw: &RawComponentManifest, app_dir: &Path) -> Result<()> {
+    let b = match raw.build.as_ref() {
+        Some(b) => b,
+        None => return Ok(()),
+    };
+
+    let cwd = match &b.workdir {
+        Some(w) => app_dir.join(w),
+        None => app_dir.to_path_buf(),
+    };
+
+    log::info!(
+        "Executing the build command for component {}: {}",
+        raw.id,
+        b.command
+    );
+
+    // Output is inherited from the parent, so it is streamed as the command runs.
+    let status = shell_command(&b.command)
+        .current_dir(&cwd)
+        .kill_on_drop(true)
+        .status()
+        .await
+        .with_context(|| {
+            format!(
+                "Cannot spawn build process for component {} in {}",
+                raw.id,
+                cwd.display()
+            )
+        })?;
+
+    if !status.success() {
+        bail!(
+            "Build command for component {} failed with status {}",
+            raw.id,
+            status
+        );
+    }
+
+    Ok(())
+}
+
+/// Create a command that runs the given command line in the platform shell.
+fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
+
+fn parent_dir(file: &Path) -> Result<PathBuf> {
+    let path_buf = file
+        .parent()
+        .ok_or_else(|| {
+            anyhow!(
+                "Failed to get containing directory for app file '{}'",
+                file.display()
+            )
+        })?
+        .to_owned();
+    Ok(path_buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_failing_build_reports_component() {
+        const MANIFEST: &str = "tests/failing-build.toml";
+
+        let err = build(Path::new(MANIFEST), false)
+            .await
+            .expect_err("Expected build to fail");
+        assert!(
+            err.to_string().contains("fails"),
+            "Expected error to mention the component ID"
+        );
+    }
+}
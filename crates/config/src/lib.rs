@@ -2,10 +2,14 @@
 
 #![deny(missing_docs)]
 
+mod http_hosts;
+
+pub use http_hosts::{parse_allowed_network, AllowedHttpHost, ALLOW_ALL_HTTP_HOSTS};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
-    fmt::{Debug, Formatter},
+    fmt::{Debug, Display, Formatter},
     path::PathBuf,
 };
 
@@ -18,6 +22,8 @@ pub struct Application<T> {
     pub components: Vec<T>,
     /// Configuration for the components' triggers.
     pub component_triggers: ComponentMap<TriggerConfig>,
+    /// Key-value stores available to the application's components.
+    pub key_value_stores: Vec<KeyValueStoreConfig>,
 }
 
 /// Spin API version.
@@ -77,10 +83,12 @@ pub enum ApplicationOrigin {
         /// Bindle server URL.
         server: String,
     },
+    /// The application was loaded from the specified OCI reference.
+    Oci(String),
 }
 
 /// The trigger type.
-#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "camelCase", tag = "type")]
 pub enum ApplicationTrigger {
     /// HTTP trigger type.
@@ -90,7 +98,7 @@ pub enum ApplicationTrigger {
 }
 
 /// HTTP trigger configuration.
-#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
 pub struct HttpTriggerConfiguration {
     /// Base path for the HTTP application.
     pub base: String,
@@ -102,7 +110,7 @@ impl Default for HttpTriggerConfiguration {
 }
 
 /// Redis trigger configuration.
-#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
 pub struct RedisTriggerConfiguration {
     /// Address of Redis server.
     pub address: String,
@@ -133,8 +141,102 @@ pub struct WasmConfig {
     pub environment: HashMap<String, String>,
     /// List of directory mounts that need to be mapped inside the WebAssembly module.
     pub mounts: Vec<DirectoryMount>,
-    /// Optional list of HTTP hosts the component is allowed to connect.
+    /// Optional list of HTTP hosts the component is allowed to connect, see
+    /// [`AllowedHttpHost`] for the format of the entries.
     pub allowed_http_hosts: Vec<String>,
+    /// Optional list of Redis server addresses the component is allowed to connect.
+    pub allowed_redis_addresses: Vec<String>,
+    /// Names of the application key-value stores the component is allowed to use.
+    pub key_value_stores: Vec<String>,
+    /// Limits of the outbound HTTP requests of the component.
+    pub outbound_http: OutboundHttpConfig,
+}
+
+/// Limits of the outbound HTTP requests of a component. Limits that are
+/// not set do not apply.
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct OutboundHttpConfig {
+    /// Maximum time to connect to a server, in milliseconds.
+    pub connect_timeout_ms: Option<u64>,
+    /// Maximum time to complete a request, from connecting to the server to
+    /// reading the entire response, in milliseconds.
+    pub timeout_ms: Option<u64>,
+    /// Maximum size of a response body, in bytes.
+    pub max_response_bytes: Option<u64>,
+    /// Maximum number of requests sent at the same time by all the instances
+    /// of the component.
+    pub max_concurrent_requests: Option<u32>,
+    /// Maximum number of redirects followed for a request. Redirects are not
+    /// followed if set to 0, and up to 10 redirects are followed if not set.
+    pub max_redirects: Option<u32>,
+    /// Loopback, link-local and private networks the component is allowed to
+    /// send requests to, which are denied by default. See
+    /// [`parse_allowed_network`] for the format of the entries.
+    #[serde(default)]
+    pub allowed_networks: Vec<String>,
+}
+
+/// Recording of the outbound HTTP requests of an application to a cassette
+/// file, or replaying of the recorded responses, for deterministic tests.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OutboundHttpCassette {
+    /// Requests are sent, and recorded with their responses to the file,
+    /// replacing any previous recording.
+    Record {
+        /// Path of the cassette file.
+        path: PathBuf,
+        /// Request headers whose values must match for a recorded response to
+        /// be replayed, in addition to the method, URL and body of the request.
+        match_headers: Vec<String>,
+    },
+    /// Requests are not sent, and the responses recorded to the file for
+    /// matching requests are returned instead.
+    Replay {
+        /// Path of the cassette file.
+        path: PathBuf,
+    },
+}
+
+/// A named key-value store declared by an application.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct KeyValueStoreConfig {
+    /// Name used by components to refer to the store.
+    pub name: String,
+    /// The backend holding the data of the store.
+    pub backend: KeyValueBackend,
+    /// For the `sqlite` and `file` backends, the database file or the
+    /// directory holding the data.
+    pub path: Option<PathBuf>,
+    /// For the `redis` backend, the address of the Redis server.
+    pub address: Option<String>,
+}
+
+/// The backend of a key-value store.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyValueBackend {
+    /// Data is kept in memory and lost when the application stops.
+    Memory,
+    /// Data is kept in a local SQLite database.
+    Sqlite,
+    /// Data is kept in a local directory, one file per key.
+    File,
+    /// Data is kept in a Redis server.
+    Redis,
+}
+
+impl Display for KeyValueBackend {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        let name = match self {
+            Self::Memory => "memory",
+            Self::Sqlite => "sqlite",
+            Self::File => "file",
+            Self::Redis => "redis",
+        };
+        f.write_str(name)
+    }
 }
 
 /// Directory mount for the assets of a component.
@@ -144,6 +246,8 @@ pub struct DirectoryMount {
     pub guest: String,
     /// Host directory source for mounting inside the module.
     pub host: PathBuf,
+    /// Whether the module is prevented from modifying the directory.
+    pub read_only: bool,
 }
 
 /// Source for the entrypoint Wasm module of a component.
@@ -172,7 +276,7 @@ impl Debug for ModuleSource {
     }
 }
 /// Configuration for the HTTP trigger.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 pub struct HttpConfig {
     /// HTTP route the component will be invoked for.
     pub route: String,
@@ -194,7 +298,7 @@ impl Default for HttpConfig {
 /// or the Wagi CGI interface.
 ///
 /// If an executor is not specified, the inferred default is `HttpExecutor::Spin`.
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, JsonSchema, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "camelCase", tag = "type")]
 pub enum HttpExecutor {
     /// The component implements the Spin HTTP interface.
@@ -210,7 +314,7 @@ impl Default for HttpExecutor {
 }
 
 /// Wagi specific configuration for the http executor.
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, JsonSchema, Serialize)]
 #[serde(default, deny_unknown_fields, rename_all = "camelCase")]
 pub struct WagiConfig {
     /// The name of the entrypoint.
@@ -241,7 +345,7 @@ impl Default for WagiConfig {
 }
 
 /// Configuration for the Redis trigger.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 pub struct RedisConfig {
     /// Redis channel to subscribe.
     pub channel: String,
@@ -252,7 +356,7 @@ pub struct RedisConfig {
 /// The executor for the Redis component.
 ///
 /// If an executor is not specified, the inferred default is `RedisExecutor::Spin`.
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, JsonSchema, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "camelCase", tag = "type")]
 pub enum RedisExecutor {
     /// The component implements the Spin Redis interface.
@@ -266,7 +370,7 @@ impl Default for RedisExecutor {
 }
 
 /// Trigger configuration.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "camelCase", untagged)]
 pub enum TriggerConfig {
     /// HTTP trigger configuration
@@ -7,12 +7,16 @@ pub mod io;
 
 use anyhow::{bail, Context, Result};
 use io::IoStreamRedirects;
-use spin_config::{Application, CoreComponent, DirectoryMount, ModuleSource};
+use spin_config::{
+    AllowedHttpHost, Application, CoreComponent, DirectoryMount, KeyValueStoreConfig, ModuleSource,
+    OutboundHttpCassette,
+};
+use spin_kv::KeyValueStores;
 use std::{collections::HashMap, io::Write, path::PathBuf, sync::Arc};
 use tokio::task::JoinHandle;
 use tokio::time::{sleep, Duration};
 use tracing::{instrument, log};
-use wasi_common::WasiCtx;
+use wasi_common::{dir::DirCaps, file::FileCaps, Table, WasiCtx};
 use wasmtime::{Engine, Instance, InstancePre, Linker, Module, Store};
 use wasmtime_wasi::{ambient_authority, Dir, WasiCtxBuilder};
 
@@ -27,6 +31,11 @@ pub struct ExecutionContextConfiguration {
     pub label: String,
     /// Log directory on host.
     pub log_dir: Option<PathBuf>,
+    /// Key-value stores declared by the application.
+    pub key_value_stores: Vec<KeyValueStoreConfig>,
+    /// Cassette the outbound HTTP requests of the components are recorded to
+    /// or replayed from, if any.
+    pub outbound_http_cassette: Option<OutboundHttpCassette>,
 }
 
 impl From<Application<CoreComponent>> for ExecutionContextConfiguration {
@@ -34,6 +43,7 @@ impl From<Application<CoreComponent>> for ExecutionContextConfiguration {
         Self {
             components: app.components,
             label: app.info.name,
+            key_value_stores: app.key_value_stores,
             ..Default::default()
         }
     }
@@ -45,9 +55,13 @@ pub struct RuntimeContext<T> {
     /// WASI context data.
     pub wasi: Option<WasiCtx>,
     /// Experimental outbound HTTP configuration.
-    pub experimental_http: Option<wasi_experimental_http_wasmtime::HttpCtx>,
+    pub experimental_http: Option<wasi_outbound_http::ExperimentalHttp>,
     /// Outbound HTTP configuration.
     pub outbound_http: Option<wasi_outbound_http::OutboundHttp>,
+    /// Outbound Redis configuration.
+    pub outbound_redis: Option<outbound_redis::OutboundRedis>,
+    /// Key-value stores the component is allowed to use.
+    pub key_value: Option<spin_kv::KeyValue>,
     /// Generic runtime data that can be configured by specialized engines.
     pub data: Option<T>,
 }
@@ -58,6 +72,7 @@ pub struct Builder<T: Default> {
     linker: Linker<RuntimeContext<T>>,
     store: Store<RuntimeContext<T>>,
     engine: Engine,
+    key_value_stores: KeyValueStores,
 }
 
 impl<T: Default> Builder<T> {
@@ -87,6 +102,7 @@ impl<T: Default> Builder<T> {
             linker,
             store,
             engine,
+            key_value_stores: Default::default(),
         })
     }
 
@@ -98,10 +114,9 @@ impl<T: Default> Builder<T> {
 
     /// Configures the ability to execute outbound HTTP requests.
     pub fn link_http(&mut self) -> Result<&mut Self> {
-        wasi_experimental_http_wasmtime::HttpState::new()?
-            .add_to_linker(&mut self.linker, |ctx| {
-                ctx.experimental_http.as_ref().unwrap()
-            })?;
+        wasi_outbound_http::experimental::add_to_linker(&mut self.linker, |ctx| {
+            ctx.experimental_http.as_mut().unwrap()
+        })?;
 
         wasi_outbound_http::add_to_linker(&mut self.linker, |ctx| {
             ctx.outbound_http.as_mut().unwrap()
@@ -110,9 +125,31 @@ impl<T: Default> Builder<T> {
         Ok(self)
     }
 
+    /// Configures the ability to send commands to Redis servers.
+    pub fn link_redis(&mut self) -> Result<&mut Self> {
+        outbound_redis::add_to_linker(&mut self.linker, |ctx| {
+            ctx.outbound_redis.as_mut().unwrap()
+        })?;
+
+        Ok(self)
+    }
+
+    /// Opens the key-value stores of the application and configures the
+    /// ability to use them.
+    pub fn link_kv(&mut self) -> Result<&mut Self> {
+        self.key_value_stores = KeyValueStores::open(&self.config.key_value_stores)?;
+        spin_kv::add_to_linker(&mut self.linker, |ctx| ctx.key_value.as_mut().unwrap())?;
+        Ok(self)
+    }
+
     /// Builds a new instance of the execution context.
     #[instrument(skip(self))]
     pub async fn build(&mut self) -> Result<ExecutionContext<T>> {
+        let cassette = match &self.config.outbound_http_cassette {
+            Some(config) => Some(Arc::new(wasi_outbound_http::Cassette::open(config)?)),
+            None => None,
+        };
+
         let mut components = HashMap::new();
         for c in &self.config.components {
             let core = c.clone();
@@ -145,11 +182,26 @@ impl<T: Default> Builder<T> {
             let pre = Arc::new(self.linker.instantiate_pre(&mut self.store, &module)?);
             log::trace!("Created pre-instance from module for component {}.", &c.id);
 
-            components.insert(c.id.clone(), Component { core, pre });
+            let http_client = wasi_outbound_http::HttpClient::new(
+                c.wasm.outbound_http.clone(),
+                AllowedHttpHost::parse_all(&c.wasm.allowed_http_hosts)?,
+            )
+            .with_context(|| format!("Cannot create HTTP client for component {}", &c.id))?
+            .with_cassette(cassette.clone());
+
+            components.insert(
+                c.id.clone(),
+                Component {
+                    core,
+                    pre,
+                    http_client,
+                },
+            );
         }
 
         let config = self.config.clone();
         let engine = self.engine.clone();
+        let key_value_stores = self.key_value_stores.clone();
 
         log::trace!("Execution context initialized.");
 
@@ -157,6 +209,7 @@ impl<T: Default> Builder<T> {
             config,
             engine,
             components,
+            key_value_stores,
         })
     }
 
@@ -165,7 +218,13 @@ impl<T: Default> Builder<T> {
         config: ExecutionContextConfiguration,
     ) -> Result<ExecutionContext<T>> {
         let _sloth_warning = warn_if_slothful();
-        Self::new(config)?.link_wasi()?.link_http()?.build().await
+        Self::new(config)?
+            .link_wasi()?
+            .link_http()?
+            .link_redis()?
+            .link_kv()?
+            .build()
+            .await
     }
 }
 
@@ -176,6 +235,9 @@ pub struct Component<T: Default> {
     pub core: CoreComponent,
     /// The pre-instance of the component
     pub pre: Arc<InstancePre<RuntimeContext<T>>>,
+    /// Client for the outbound HTTP requests of the component, shared by all
+    /// its instances so that connections to the same hosts are reused.
+    pub http_client: wasi_outbound_http::HttpClient,
 }
 
 /// A generic execution context for WebAssembly components.
@@ -187,6 +249,8 @@ pub struct ExecutionContext<T: Default> {
     pub engine: Engine,
     /// Collection of pre-initialized (and already linked) components.
     pub components: HashMap<String, Component<T>>,
+    /// Key-value stores of the application, shared by all components.
+    pub key_value_stores: KeyValueStores,
 }
 
 impl<T: Default> ExecutionContext<T> {
@@ -292,25 +356,41 @@ impl<T: Default> ExecutionContext<T> {
             None => wasi_ctx = wasi_ctx.inherit_stdio(),
         };
 
-        for dir in dirs {
-            let guest = dir.guest;
-            let host = dir.host;
-            wasi_ctx =
-                wasi_ctx.preopened_dir(Dir::open_ambient_dir(host, ambient_authority())?, guest)?;
+        let (read_only_dirs, dirs): (Vec<_>, Vec<_>) = dirs.into_iter().partition(|d| d.read_only);
+        for dir in &dirs {
+            wasi_ctx = wasi_ctx.preopened_dir(open_dir(dir)?, &dir.guest)?;
         }
 
         // We basically have to support both versions of outbound HTTP for the time being.
-        let experimental_http = wasi_experimental_http_wasmtime::HttpCtx {
-            allowed_hosts: Some(component.core.wasm.allowed_http_hosts.clone()),
-            max_concurrent_requests: None,
-        };
-        let outbound_http = wasi_outbound_http::OutboundHttp {
-            allowed_hosts: Some(component.core.wasm.allowed_http_hosts.clone()),
-        };
+        let experimental_http =
+            wasi_outbound_http::ExperimentalHttp::new(component.http_client.clone());
+        let outbound_http = wasi_outbound_http::OutboundHttp::new(component.http_client.clone());
+
+        let mut wasi = wasi_ctx.build();
+        // Read-only directories are inserted after the writable preopened
+        // directories, without the capabilities to modify them, each at the
+        // first descriptor the WASI context has not assigned.
+        for dir in &read_only_dirs {
+            let fd = free_fd(wasi.table())?;
+            wasi.insert_dir(
+                fd,
+                Box::new(wasi_cap_std_sync::dir::Dir::from_cap_std(open_dir(dir)?)),
+                read_only_dir_caps(),
+                read_only_file_caps(),
+                PathBuf::from(&dir.guest),
+            );
+        }
 
-        ctx.wasi = Some(wasi_ctx.build());
+        ctx.wasi = Some(wasi);
         ctx.experimental_http = Some(experimental_http);
         ctx.outbound_http = Some(outbound_http);
+        ctx.outbound_redis = Some(outbound_redis::OutboundRedis::new(
+            component.core.wasm.allowed_redis_addresses.clone(),
+        ));
+        ctx.key_value = Some(
+            self.key_value_stores
+                .for_component(&component.core.wasm.key_value_stores),
+        );
         ctx.data = data;
 
         let store = Store::new(&self.engine, ctx);
@@ -342,6 +422,39 @@ impl<T: Default> ExecutionContext<T> {
     }
 }
 
+fn open_dir(dir: &DirectoryMount) -> Result<Dir> {
+    Dir::open_ambient_dir(&dir.host, ambient_authority())
+        .with_context(|| format!("Cannot open directory '{}'", dir.host.display()))
+}
+
+/// Returns the lowest file descriptor above the standard streams that is not
+/// in use in the WASI table.
+fn free_fd(table: &Table) -> Result<u32> {
+    (3..=u32::MAX)
+        .find(|fd| !table.contains_key(*fd))
+        .context("No free file descriptor for preopened directory")
+}
+
+/// Capabilities of read-only directories: files and subdirectories can be
+/// opened and listed, but not created, renamed or removed.
+fn read_only_dir_caps() -> DirCaps {
+    DirCaps::OPEN
+        | DirCaps::READDIR
+        | DirCaps::READLINK
+        | DirCaps::PATH_FILESTAT_GET
+        | DirCaps::FILESTAT_GET
+}
+
+/// Capabilities of files in read-only directories: they can be read, but not written.
+fn read_only_file_caps() -> FileCaps {
+    FileCaps::READ
+        | FileCaps::SEEK
+        | FileCaps::TELL
+        | FileCaps::ADVISE
+        | FileCaps::FILESTAT_GET
+        | FileCaps::POLL_READWRITE
+}
+
 fn sanitize(name: impl AsRef<str>) -> String {
     // options block copied from sanitize_filename project readme
     let options = sanitize_filename::Options {
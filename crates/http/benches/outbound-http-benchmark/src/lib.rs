@@ -0,0 +1,39 @@
+wit_bindgen_rust::export!("../../../../wit/ephemeral/spin-http.wit");
+wit_bindgen_rust::import!("../../../../wit/ephemeral/wasi-outbound-http.wit");
+
+struct SpinHttp {}
+
+impl spin_http::SpinHttp for SpinHttp {
+    fn handle_http_request(req: spin_http::Request) -> spin_http::Response {
+        let mut url = None;
+        let mut count = 1;
+        for param in req.params {
+            match (param.0.as_str(), param.1) {
+                // url=<url> param is the destination of the outbound requests
+                ("url", u) => url = Some(u),
+                // count=<n> param is the number of outbound requests to send
+                ("count", n) => count = n.parse().expect("invalid count"),
+                _ => (),
+            }
+        }
+        let url = url.expect("missing url");
+
+        for _ in 0..count {
+            let req = wasi_outbound_http::Request {
+                method: wasi_outbound_http::Method::Get,
+                uri: &url,
+                headers: &[],
+                params: &[],
+                body: None,
+            };
+            let res = wasi_outbound_http::request(req).expect("outbound request failed");
+            assert_eq!(res.status, 200);
+        }
+
+        spin_http::Response {
+            status: 200,
+            headers: None,
+            body: None,
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::{convert::Infallible, net::SocketAddr};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use http::Request;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Response, Server,
+};
+use spin_config::OutboundHttpConfig;
+use spin_http_engine::HttpTrigger;
+use spin_testing::{assert_http_response_success, TestConfig};
+use tokio::runtime::Runtime;
+
+criterion_main!(benches);
+criterion_group!(benches, bench_outbound_requests);
+
+// Benchmark the time to process a request for a component that makes a
+// number of outbound HTTP requests to a local server, which measures the
+// cost of each outbound request, including setting up its connection.
+fn bench_outbound_requests(c: &mut Criterion) {
+    let async_runtime = Runtime::new().unwrap();
+
+    let addr = async_runtime.block_on(start_server());
+    let url = format!("http://{}/", addr);
+    let trigger = async_runtime.block_on(
+        TestConfig::default()
+            .test_program("outbound-http-benchmark.wasm")
+            .http_trigger(Default::default())
+            .allowed_http_hosts(vec![url.clone()])
+            .outbound_http(OutboundHttpConfig {
+                allowed_networks: vec!["127.0.0.1".to_string()],
+                ..Default::default()
+            })
+            .build_http_trigger(),
+    );
+
+    let mut group = c.benchmark_group("outbound-http");
+    for count in [1, 10, 50] {
+        group.bench_function(format!("requests-{}", count), |b| {
+            b.to_async(&async_runtime)
+                .iter(|| handle_request(&trigger, &url, count));
+        });
+    }
+}
+
+// Helpers
+
+async fn handle_request(trigger: &HttpTrigger, url: &str, count: u32) {
+    let req = Request::get(format!("/?url={}&count={}", url, count))
+        .body(Default::default())
+        .unwrap();
+    let resp = trigger
+        .handle(req, "127.0.0.1:55555".parse().unwrap())
+        .await
+        .unwrap();
+    assert_http_response_success(&resp);
+}
+
+// Starts a server responding to all requests with an empty body, and returns its address.
+async fn start_server() -> SocketAddr {
+    let make_service = make_service_fn(|_| async {
+        Ok::<_, Infallible>(service_fn(|_| async {
+            Ok::<_, Infallible>(Response::new(Body::empty()))
+        }))
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    addr
+}
@@ -21,7 +21,10 @@ use hyper::{
     service::{make_service_fn, service_fn},
     Body, Request, Response, Server,
 };
-use spin_config::{Application, ComponentMap, CoreComponent, HttpConfig, HttpTriggerConfiguration};
+use spin_config::{
+    Application, ComponentMap, CoreComponent, HttpConfig, HttpTriggerConfiguration,
+    OutboundHttpCassette,
+};
 use spin_engine::{Builder, ExecutionContextConfiguration};
 use spin_http::SpinHttpData;
 use std::{future::ready, net::SocketAddr, path::PathBuf, sync::Arc};
@@ -64,6 +67,7 @@ impl HttpTrigger {
         app: Application<CoreComponent>,
         tls: Option<TlsConfig>,
         log_dir: Option<PathBuf>,
+        outbound_http_cassette: Option<OutboundHttpCassette>,
     ) -> Result<Self> {
         let trigger_config = app
             .info
@@ -83,6 +87,7 @@ impl HttpTrigger {
 
         let config = ExecutionContextConfiguration {
             log_dir,
+            outbound_http_cassette,
             ..app.into()
         };
         let engine = Arc::new(Builder::build_default(config).await?);
@@ -400,7 +405,7 @@ pub(crate) trait HttpExecutor: Clone + Send + Sync + 'static {
 mod tests {
     use super::*;
     use anyhow::Result;
-    use spin_config::{HttpConfig, HttpExecutor};
+    use spin_config::{HttpConfig, HttpExecutor, OutboundHttpConfig};
     use spin_testing::test_socket_addr;
     use std::{collections::BTreeMap, sync::Once};
 
@@ -539,7 +544,7 @@ mod tests {
             })
             .build_configuration();
 
-        let trigger = HttpTrigger::new("".to_string(), cfg, None, None).await?;
+        let trigger = HttpTrigger::new("".to_string(), cfg, None, None, None).await?;
 
         let body = Body::from("Fermyon".as_bytes().to_vec());
         let req = http::Request::post("https://myservice.fermyon.dev/test?abc=def")
@@ -568,7 +573,7 @@ mod tests {
             })
             .build_configuration();
 
-        let trigger = HttpTrigger::new("".to_string(), cfg, None, None).await?;
+        let trigger = HttpTrigger::new("".to_string(), cfg, None, None, None).await?;
 
         let body = Body::from("Fermyon".as_bytes().to_vec());
         let req = http::Request::builder()
@@ -596,4 +601,112 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_outbound_http_record_and_replay() -> Result<()> {
+        init();
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("cassette.json");
+
+        let (addr, server) = start_outbound_server(|_| async { Response::new(Body::empty()) });
+        let url = format!("http://{}/", addr);
+        let mut cfg = spin_testing::TestConfig::default();
+        cfg.test_program("outbound-http-benchmark.wasm")
+            .http_trigger(Default::default())
+            .allowed_http_hosts(vec![url.clone()])
+            .outbound_http(OutboundHttpConfig {
+                allowed_networks: vec!["127.0.0.1".to_string()],
+                ..Default::default()
+            });
+        let req = || {
+            http::Request::get(format!("/?url={}&count=2", url))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let res = cfg
+            .outbound_http_cassette(OutboundHttpCassette::Record {
+                path: path.clone(),
+                match_headers: vec![],
+            })
+            .handle_http_request(req())
+            .await?;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // Once the server is stopped, requests only succeed when replayed.
+        server.abort();
+        let _ = server.await;
+        let res = cfg
+            .outbound_http_cassette(OutboundHttpCassette::Replay { path })
+            .handle_http_request(req())
+            .await?;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_experimental_http_library() -> Result<()> {
+        init();
+
+        // Responds with the body and the `x-custom-foo` header of the request.
+        let (addr, _) = start_outbound_server(|req| async move {
+            let foo = req.headers()["x-custom-foo"].clone();
+            let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            Response::builder()
+                .header("content-type", "text/plain")
+                .header("x-custom-foo", foo)
+                .body(Body::from(body))
+                .unwrap()
+        });
+
+        let url = format!("http://{}/", addr);
+        let mut cfg = spin_testing::TestConfig::default();
+        cfg.test_program("experimental-http-test.wasm")
+            .http_trigger(Default::default())
+            .allowed_http_hosts(vec![url.clone()])
+            .outbound_http(OutboundHttpConfig {
+                allowed_networks: vec!["127.0.0.1".to_string()],
+                ..Default::default()
+            });
+        let send = |url: String| {
+            let req = http::Request::get(format!("/?url={}", url))
+                .body(Body::empty())
+                .unwrap();
+            cfg.handle_http_request(req)
+        };
+
+        let res = send(url).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(res.into_body()).await?;
+        assert_eq!(body.as_ref(), b"200\ntext/plain\nbar\nFermyon");
+
+        // Destinations the component is not allowed to reach are denied.
+        let res = send(format!("http://localhost:{}/", addr.port())).await?;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        Ok(())
+    }
+
+    // Starts a server responding to all requests with the handler.
+    fn start_outbound_server<F, Fut>(
+        handler: F,
+    ) -> (SocketAddr, tokio::task::JoinHandle<hyper::Result<()>>)
+    where
+        F: Fn(Request<Body>) -> Fut + Clone + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Response<Body>> + Send + 'static,
+    {
+        let make_service = make_service_fn(move |_| {
+            let handler = handler.clone();
+            async move {
+                Ok::<_, std::convert::Infallible>(service_fn(move |req| {
+                    let res = handler(req);
+                    async move { Ok::<_, std::convert::Infallible>(res.await) }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        (server.local_addr(), tokio::spawn(server))
+    }
 }
@@ -29,18 +29,24 @@ pub(crate) struct Router {
 impl Router {
     /// Builds a router based on application configuration.
     pub(crate) fn build(app: &Application<CoreComponent>) -> Result<Self> {
-        let app_trigger = app.info.trigger.as_http().unwrap().clone();
+        let app_trigger = match app.info.trigger.as_http() {
+            Some(t) => t.clone(),
+            None => bail!("Application trigger is not HTTP"),
+        };
         let routes = app
             .components
             .iter()
             .map(|c| {
-                let trigger = app.component_triggers.get(c).unwrap().as_http().unwrap();
-                (
+                let trigger = match app.component_triggers.get(c).and_then(|t| t.as_http()) {
+                    Some(t) => t,
+                    None => bail!("Expected HTTP configuration for component {}", c.id),
+                };
+                Ok((
                     RoutePattern::from(&app_trigger.base, &trigger.route),
                     c.clone(),
-                )
+                ))
             })
-            .collect();
+            .collect::<Result<_>>()?;
 
         log::trace!(
             "Constructed router for application {}: {:?}",
@@ -0,0 +1,54 @@
+// Sends a request with the WASI experimental HTTP library, to check that the
+// host functions of Spin are compatible with the upstream guest library.
+
+wit_bindgen_rust::export!("../../../../wit/ephemeral/spin-http.wit");
+
+struct SpinHttp {}
+
+impl spin_http::SpinHttp for SpinHttp {
+    fn handle_http_request(req: spin_http::Request) -> spin_http::Response {
+        let url = req
+            .params
+            .into_iter()
+            .find_map(|(name, value)| (name == "url").then(|| value))
+            .expect("missing url");
+
+        let req = http::Request::builder()
+            .method("POST")
+            .uri(&url)
+            .header("x-custom-foo", "bar")
+            .body(Some(bytes::Bytes::from("Fermyon")))
+            .unwrap();
+        let (status, body) = match send(req) {
+            Ok(body) => (200, body),
+            Err(e) => (500, e.to_string()),
+        };
+
+        spin_http::Response {
+            status,
+            headers: None,
+            body: Some(body.into_bytes()),
+        }
+    }
+}
+
+/// Sends the request and describes the response, one line per property.
+fn send(req: http::Request<Option<bytes::Bytes>>) -> anyhow::Result<String> {
+    let mut res = wasi_experimental_http::request(req)?;
+    let content_type = res.header_get("content-type".to_string())?;
+    let headers = res.headers_get_all()?;
+    let echoed = headers
+        .get("x-custom-foo")
+        .map(|value| value.to_str())
+        .transpose()?
+        .unwrap_or_default()
+        .to_string();
+    let body = res.body_read_all()?;
+    Ok(format!(
+        "{}\n{}\n{}\n{}",
+        res.status_code.as_u16(),
+        content_type,
+        echoed,
+        String::from_utf8(body)?
+    ))
+}
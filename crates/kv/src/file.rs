@@ -0,0 +1,106 @@
+use crate::Store;
+use anyhow::{anyhow, bail, Context, Result};
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+/// The maximum length of a key, in bytes. File names are limited to 255 bytes
+/// on most filesystems, and each byte of a key takes two hex digits.
+pub const MAX_FILE_KEY_LEN: usize = 127;
+
+/// A store keeping its data in a local directory, one file per key.
+///
+/// File names are the hex encoding of the keys, so that keys can contain
+/// any character, including path separators. Keys are limited to
+/// [`MAX_FILE_KEY_LEN`] bytes.
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    /// Opens the store in the given directory, creating it if needed.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)
+            .with_context(|| anyhow!("Cannot create directory '{}'", dir.display()))?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        if key.len() > MAX_FILE_KEY_LEN {
+            bail!(
+                "Key is {} bytes long, but the 'file' backend only supports keys of up to {} bytes",
+                key.len(),
+                MAX_FILE_KEY_LEN
+            );
+        }
+        Ok(self.dir.join(encode(key)))
+    }
+}
+
+impl Store for FileStore {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.path(key)?) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, key: &str, value: &[u8]) -> Result<()> {
+        // Write to a temporary file first, so that readers never see a
+        // partially written value.
+        let mut file = tempfile::NamedTempFile::new_in(&self.dir)?;
+        file.write_all(value)?;
+        file.persist(self.path(key)?)?;
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        match fs::remove_file(self.path(key)?) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.path(key)?.is_file())
+    }
+
+    fn list_keys(&self) -> Result<Vec<String>> {
+        let mut keys = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            // Skip temporary files and anything else that is not a key.
+            if let Some(key) = entry.file_name().to_str().and_then(decode) {
+                keys.push(key);
+            }
+        }
+        Ok(keys)
+    }
+}
+
+fn encode(key: &str) -> String {
+    key.bytes().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode(name: &str) -> Option<String> {
+    if name.is_empty() {
+        return None;
+    }
+    let bytes = name
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => Some(hex_digit(*high)? << 4 | hex_digit(*low)?),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|d| d as u8)
+}
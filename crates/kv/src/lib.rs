@@ -0,0 +1,257 @@
+//! Key-value stores for Spin components.
+//!
+//! Applications declare named stores in their manifest, and components use
+//! the stores they are allowed to through the `spin-kv` interface.
+
+#![deny(missing_docs)]
+
+mod file;
+mod memory;
+mod redis;
+mod sqlite;
+
+use anyhow::{anyhow, bail, Context, Result};
+use spin_config::{KeyValueBackend, KeyValueStoreConfig};
+use spin_kv::*;
+use std::{collections::HashMap, sync::Arc};
+
+pub use crate::{file::FileStore, memory::MemoryStore, redis::RedisStore, sqlite::SqliteStore};
+pub use spin_kv::add_to_linker;
+
+wit_bindgen_wasmtime::export!("../../wit/ephemeral/spin-kv.wit");
+
+/// A backend holding the data of a key-value store.
+pub trait Store: Send + Sync {
+    /// Returns the value stored for the key, if any.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+    /// Stores the value for the key, replacing any previous value.
+    fn set(&self, key: &str, value: &[u8]) -> Result<()>;
+    /// Removes the key. Removing a key that does not exist is not an error.
+    fn delete(&self, key: &str) -> Result<()>;
+    /// Returns whether the key exists.
+    fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.get(key)?.is_some())
+    }
+    /// Returns all the keys of the store.
+    fn list_keys(&self) -> Result<Vec<String>>;
+}
+
+/// The key-value stores of an application, opened once and shared by all
+/// the instances of its components.
+#[derive(Clone, Default)]
+pub struct KeyValueStores {
+    stores: HashMap<String, Arc<dyn Store>>,
+}
+
+impl KeyValueStores {
+    /// Opens the stores declared by an application.
+    pub fn open(configs: &[KeyValueStoreConfig]) -> Result<Self> {
+        let mut stores = HashMap::new();
+        for config in configs {
+            let store = open_store(config)
+                .with_context(|| anyhow!("Cannot open key-value store '{}'", config.name))?;
+            if stores.insert(config.name.clone(), store).is_some() {
+                bail!(
+                    "Key-value store '{}' is declared more than once",
+                    config.name
+                );
+            }
+        }
+        Ok(Self { stores })
+    }
+
+    /// Adds a store, replacing any store with the same name.
+    pub fn insert(&mut self, name: impl Into<String>, store: Arc<dyn Store>) {
+        self.stores.insert(name.into(), store);
+    }
+
+    /// Returns the host component giving access to the given stores only.
+    pub fn for_component(&self, names: &[String]) -> KeyValue {
+        let stores = self
+            .stores
+            .iter()
+            .filter(|(name, _)| names.contains(name))
+            .map(|(name, store)| (name.clone(), store.clone()))
+            .collect();
+        KeyValue { stores }
+    }
+}
+
+fn open_store(config: &KeyValueStoreConfig) -> Result<Arc<dyn Store>> {
+    let path = || {
+        config
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("The '{}' backend requires a path", config.backend))
+    };
+    Ok(match config.backend {
+        KeyValueBackend::Memory => Arc::new(MemoryStore::default()),
+        KeyValueBackend::Sqlite => Arc::new(SqliteStore::open(path()?)?),
+        KeyValueBackend::File => Arc::new(FileStore::open(path()?)?),
+        KeyValueBackend::Redis => {
+            let address = config
+                .address
+                .as_ref()
+                .ok_or_else(|| anyhow!("The 'redis' backend requires an address"))?;
+            Arc::new(RedisStore::open(&config.name, address)?)
+        }
+    })
+}
+
+/// Host component for the `spin-kv` interface, holding the stores a
+/// component is allowed to use.
+#[derive(Clone, Default)]
+pub struct KeyValue {
+    stores: HashMap<String, Arc<dyn Store>>,
+}
+
+impl KeyValue {
+    fn store(&self, name: &str) -> Result<&dyn Store, Error> {
+        match self.stores.get(name) {
+            Some(store) => Ok(store.as_ref()),
+            None => {
+                tracing::log::info!("Key-value store not allowed: {}", name);
+                Err(Error::NoSuchStore)
+            }
+        }
+    }
+}
+
+impl spin_kv::SpinKv for KeyValue {
+    fn get(&mut self, store: &str, key: &str) -> Result<Vec<u8>, Error> {
+        self.store(store)?.get(key)?.ok_or(Error::NoSuchKey)
+    }
+
+    fn set(&mut self, store: &str, key: &str, value: &[u8]) -> Result<(), Error> {
+        Ok(self.store(store)?.set(key, value)?)
+    }
+
+    fn delete(&mut self, store: &str, key: &str) -> Result<(), Error> {
+        Ok(self.store(store)?.delete(key)?)
+    }
+
+    fn exists(&mut self, store: &str, key: &str) -> Result<bool, Error> {
+        Ok(self.store(store)?.exists(key)?)
+    }
+
+    fn list_keys(&mut self, store: &str) -> Result<Vec<String>, Error> {
+        Ok(self.store(store)?.list_keys()?)
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        tracing::log::error!("Key-value store error: {:?}", e);
+        Self::Runtime(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spin_kv::SpinKv;
+
+    fn config(name: &str, backend: KeyValueBackend, path: &std::path::Path) -> KeyValueStoreConfig {
+        KeyValueStoreConfig {
+            name: name.to_string(),
+            backend,
+            path: Some(path.to_path_buf()),
+            address: None,
+        }
+    }
+
+    fn check_store(store: &dyn Store) {
+        assert_eq!(store.get("missing").unwrap(), None);
+        assert!(!store.exists("missing").unwrap());
+
+        store.set("a", b"one").unwrap();
+        store.set("b/c", b"two").unwrap();
+        store.set("a", b"three").unwrap();
+        assert_eq!(store.get("a").unwrap(), Some(b"three".to_vec()));
+        assert!(store.exists("b/c").unwrap());
+
+        let mut keys = store.list_keys().unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["a".to_string(), "b/c".to_string()]);
+
+        store.delete("a").unwrap();
+        store.delete("a").unwrap();
+        assert!(!store.exists("a").unwrap());
+        assert_eq!(store.list_keys().unwrap(), vec!["b/c".to_string()]);
+    }
+
+    #[test]
+    fn test_local_backends() {
+        let dir = tempfile::tempdir().unwrap();
+        check_store(&MemoryStore::default());
+        check_store(&SqliteStore::open(dir.path().join("kv.db")).unwrap());
+        check_store(&FileStore::open(dir.path().join("kv")).unwrap());
+    }
+
+    #[test]
+    fn test_file_backend_limits_key_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::open(dir.path()).unwrap();
+
+        let longest = "k".repeat(file::MAX_FILE_KEY_LEN);
+        store.set(&longest, b"value").unwrap();
+        assert_eq!(store.list_keys().unwrap(), vec![longest]);
+
+        let too_long = "k".repeat(file::MAX_FILE_KEY_LEN + 1);
+        let err = store.set(&too_long, b"value").unwrap_err();
+        assert!(err.to_string().contains("only supports keys"), "{:#}", err);
+    }
+
+    #[test]
+    fn test_redis_backend_connects_on_first_use() {
+        // Nothing listens on port 1, so only using the store fails.
+        let store = RedisStore::open("data", "redis://127.0.0.1:1").unwrap();
+        let err = store.get("key").unwrap_err();
+        assert!(err.to_string().contains("Cannot connect"), "{:#}", err);
+    }
+
+    #[test]
+    fn test_local_backends_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        for backend in [KeyValueBackend::Sqlite, KeyValueBackend::File] {
+            let configs = vec![config(
+                "data",
+                backend,
+                &dir.path().join(backend.to_string()),
+            )];
+
+            let mut kv = KeyValueStores::open(&configs)
+                .unwrap()
+                .for_component(&["data".to_string()]);
+            kv.set("data", "key", b"value").unwrap();
+
+            let mut kv = KeyValueStores::open(&configs)
+                .unwrap()
+                .for_component(&["data".to_string()]);
+            assert_eq!(kv.get("data", "key").unwrap(), b"value".to_vec());
+        }
+    }
+
+    #[test]
+    fn test_component_only_uses_allowed_stores() {
+        let mut stores = KeyValueStores::default();
+        stores.insert("allowed", Arc::new(MemoryStore::default()));
+        stores.insert("other", Arc::new(MemoryStore::default()));
+
+        let mut kv = stores.for_component(&["allowed".to_string(), "undeclared".to_string()]);
+        kv.set("allowed", "key", b"value").unwrap();
+        assert!(matches!(
+            kv.get("allowed", "missing"),
+            Err(Error::NoSuchKey)
+        ));
+        assert!(matches!(kv.get("other", "key"), Err(Error::NoSuchStore)));
+        assert!(matches!(
+            kv.list_keys("undeclared"),
+            Err(Error::NoSuchStore)
+        ));
+
+        // Instances of components share the data of the stores.
+        let mut kv = stores.for_component(&["allowed".to_string()]);
+        assert_eq!(kv.get("allowed", "key").unwrap(), b"value".to_vec());
+    }
+}
@@ -0,0 +1,36 @@
+use crate::Store;
+use anyhow::Result;
+use std::{collections::HashMap, sync::RwLock};
+
+/// A store keeping its data in memory, for the lifetime of the application.
+#[derive(Default)]
+pub struct MemoryStore {
+    data: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl Store for MemoryStore {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.data.read().unwrap().get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: &[u8]) -> Result<()> {
+        self.data
+            .write()
+            .unwrap()
+            .insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        self.data.write().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.data.read().unwrap().contains_key(key))
+    }
+
+    fn list_keys(&self) -> Result<Vec<String>> {
+        Ok(self.data.read().unwrap().keys().cloned().collect())
+    }
+}
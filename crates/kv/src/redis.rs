@@ -0,0 +1,105 @@
+use crate::Store;
+use ::redis::{Client, Commands, Connection, RedisResult};
+use anyhow::{anyhow, Context, Result};
+use std::sync::Mutex;
+use tracing::log;
+
+/// A store keeping its data in a Redis server, under keys prefixed with the
+/// store name so that stores can share a database.
+///
+/// The store connects to the server when it is first used, and reconnects
+/// when the connection fails, so that applications start while the server is
+/// down and recover from a restart of the server or a dropped connection.
+pub struct RedisStore {
+    client: Client,
+    address: String,
+    connection: Mutex<Option<Connection>>,
+    prefix: String,
+}
+
+impl RedisStore {
+    /// Opens the named store in the Redis server at the given address, without
+    /// connecting to it yet.
+    pub fn open(name: &str, address: &str) -> Result<Self> {
+        let client = Client::open(address)
+            .with_context(|| anyhow!("Invalid Redis server address {}", address))?;
+        Ok(Self {
+            client,
+            address: address.to_owned(),
+            connection: Mutex::new(None),
+            prefix: format!("{}:", name),
+        })
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+
+    /// Runs a command, connecting first if there is no connection yet, and
+    /// reconnecting and retrying it once if the connection failed.
+    /// All the commands of the store are idempotent, so retrying them is safe.
+    fn run<T>(&self, command: impl Fn(&mut Connection) -> RedisResult<T>) -> Result<T> {
+        let mut connection = self.connection.lock().unwrap();
+        let current = match connection.take() {
+            Some(current) => current,
+            None => self.connect()?,
+        };
+        match command(connection.insert(current)) {
+            Err(e) if e.is_io_error() => {
+                log::debug!("Reconnecting to Redis server after error: {}", e);
+                *connection = None;
+                Ok(command(connection.insert(self.connect()?))?)
+            }
+            result => Ok(result?),
+        }
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        self.client
+            .get_connection()
+            .with_context(|| anyhow!("Cannot connect to Redis server at {}", self.address))
+    }
+}
+
+impl Store for RedisStore {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let key = self.key(key);
+        self.run(|c| c.get(&key))
+    }
+
+    fn set(&self, key: &str, value: &[u8]) -> Result<()> {
+        let key = self.key(key);
+        self.run(|c| c.set(&key, value))
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let key = self.key(key);
+        self.run(|c| c.del(&key))
+    }
+
+    fn exists(&self, key: &str) -> Result<bool> {
+        let key = self.key(key);
+        self.run(|c| c.exists(&key))
+    }
+
+    fn list_keys(&self) -> Result<Vec<String>> {
+        // Glob characters in the store name are escaped in the pattern.
+        let pattern = format!("{}*", escape_glob(&self.prefix));
+        let keys = self.run(|c| Ok(c.scan_match::<_, String>(&pattern)?.collect::<Vec<_>>()))?;
+        Ok(keys
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(&self.prefix).map(str::to_owned))
+            .collect())
+    }
+}
+
+fn escape_glob(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -5,6 +5,10 @@ use sha2::Digest;
 use std::path::{Path, PathBuf};
 use tokio::fs;
 
+const SPIN_HOME: &str = ".spin";
+/// The number of hex digits of the hash in the name of an application directory.
+const APP_DIR_HASH_LEN: usize = 12;
+
 /// Create the temporary directory for a component.
 pub(crate) async fn create_dir(base: impl AsRef<Path>, id: &str) -> Result<PathBuf> {
     let dir = base.as_ref().join("assets").join(component_dir(id));
@@ -16,6 +20,20 @@ pub(crate) async fn create_dir(base: impl AsRef<Path>, id: &str) -> Result<PathB
     Ok(dir)
 }
 
+/// Returns the directory holding the state of an application,
+/// `~/.spin/<app>_<hash>`. The hash of the name tells apart applications whose
+/// names differ only in unsafe characters, such as `my.app` and `my_app`.
+pub(crate) fn app_dir(app_name: &str) -> Result<PathBuf> {
+    let home = dirs::home_dir()
+        .ok_or_else(|| anyhow!("Cannot find the home directory for application data"))?;
+    let app_dir = format!(
+        "{}_{}",
+        UNSAFE_CHARACTERS.replace_all(app_name, "_"),
+        &sha256(app_name)[..APP_DIR_HASH_LEN]
+    );
+    Ok(home.join(SPIN_HOME).join(app_dir))
+}
+
 /// Get the path of a file relative to a given directory.
 pub(crate) fn to_relative(path: impl AsRef<Path>, relative_to: impl AsRef<Path>) -> Result<String> {
     let rel = path.as_ref().strip_prefix(&relative_to).with_context(|| {
@@ -96,4 +114,19 @@ mod test {
         assert!(!is_under("/foo", "/bar/baz"));
         assert!(!is_under("/foo", "/foo/../bar/baz"));
     }
+
+    #[test]
+    fn test_app_dirs_do_not_collide() {
+        let dirs = ["my.app", "my app", "my_app"]
+            .iter()
+            .map(|name| app_dir(name).unwrap())
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(dirs.len(), 3);
+        assert!(app_dir("my.app")
+            .unwrap()
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("my_app_"));
+    }
 }
@@ -12,6 +12,11 @@ use std::path::Path;
 use tokio::fs;
 use tracing::log;
 
+/// Environment variable that, when set to `1` or `true`, skips asset parcels
+/// that cannot be fetched or verified with a warning instead of failing to
+/// load the application.
+pub const ALLOW_UNVERIFIED_ASSETS_ENV: &str = "SPIN_ALLOW_UNVERIFIED_ASSETS";
+
 pub(crate) async fn prepare_component(
     reader: &BindleReader,
     bindle_id: &Id,
@@ -22,6 +27,7 @@ pub(crate) async fn prepare_component(
     let copier = Copier {
         reader: reader.clone(),
         id: bindle_id.clone(),
+        allow_unverified: allow_unverified_assets(),
     };
     copier.prepare(parcels, base_dst, component).await
 }
@@ -29,6 +35,7 @@ pub(crate) async fn prepare_component(
 pub(crate) struct Copier {
     reader: BindleReader,
     id: Id,
+    allow_unverified: bool,
 }
 
 impl Copier {
@@ -48,20 +55,42 @@ impl Copier {
         let guest = "/".to_string();
         self.copy_all(parcels, &host).await?;
 
-        Ok(DirectoryMount { host, guest })
+        Ok(DirectoryMount {
+            host,
+            guest,
+            read_only: false,
+        })
     }
 
     async fn copy_all(&self, parcels: &[Label], dir: impl AsRef<Path>) -> Result<()> {
-        match future::join_all(parcels.iter().map(|p| self.copy(p, &dir)))
+        let errors = future::join_all(parcels.iter().map(|p| self.copy(p, &dir)))
             .await
             .into_iter()
             .filter_map(|r| r.err())
-            .map(|e| log::error!("{:?}", e))
-            .count()
-        {
-            0 => Ok(()),
-            n => bail!("Error copying assets: {} file(s) not copied", n),
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            return Ok(());
+        }
+
+        if self.allow_unverified {
+            for e in &errors {
+                log::warn!("Skipping asset: {:?}", e);
+            }
+            return Ok(());
+        }
+
+        for e in &errors {
+            log::error!("{:?}", e);
         }
+        bail!(
+            "Error copying assets: {} file(s) not copied:\n{}",
+            errors.len(),
+            errors
+                .iter()
+                .map(|e| format!("  {:#}", e))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
     }
 
     async fn copy(&self, p: &Label, dir: impl AsRef<Path>) -> Result<()> {
@@ -77,7 +106,7 @@ impl Copier {
         );
         fs::create_dir_all(to.parent().expect("Cannot copy to file '/'")).await?;
         let buf =
-            self.reader.get_parcel(&p.sha256).await.with_context(|| {
+            self.reader.get_parcel(p).await.with_context(|| {
                 anyhow!("Failed to fetch asset parcel '{}@{}'", self.id, p.sha256)
             })?;
         fs::write(&to, &buf).await.with_context(|| {
@@ -91,3 +120,71 @@ impl Copier {
         Ok(())
     }
 }
+
+/// Returns whether asset parcels that cannot be fetched or verified are skipped.
+fn allow_unverified_assets() -> bool {
+    matches!(
+        std::env::var(ALLOW_UNVERIFIED_ASSETS_ENV).as_deref(),
+        Ok("1") | Ok("true")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_BINDLE_DIR: &str = "tests/standalone-bindle";
+    const TEST_BINDLE_ID: &str = "spin-module-test/1.0.0";
+
+    #[tokio::test]
+    async fn test_unverified_assets_are_only_skipped_when_allowed() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let bindle_dir = temp_dir.path().join("bindle");
+        fs_extra::dir::copy(
+            TEST_BINDLE_DIR,
+            &bindle_dir,
+            &fs_extra::dir::CopyOptions {
+                content_only: true,
+                ..Default::default()
+            },
+        )?;
+        let id: Id = TEST_BINDLE_ID.parse()?;
+        let reader = BindleReader::standalone(&bindle_dir, &id).await?;
+        let label = reader
+            .get_invoice()
+            .await?
+            .parcel
+            .unwrap()
+            .into_iter()
+            .map(|p| p.label)
+            .find(|l| l.name == "module.wasm")
+            .unwrap();
+
+        let parcel_file = bindle_dir
+            .join(id.sha())
+            .join("parcels")
+            .join(format!("{}.dat", label.sha256));
+        let mut bytes = std::fs::read(&parcel_file)?;
+        bytes[0] ^= 0xff;
+        std::fs::write(&parcel_file, bytes)?;
+
+        let dst = temp_dir.path().join("assets");
+        let copier = |allow_unverified| Copier {
+            reader: reader.clone(),
+            id: id.clone(),
+            allow_unverified,
+        };
+        let parcels = vec![label];
+
+        let err = copier(false)
+            .copy_all(&parcels, &dst)
+            .await
+            .expect_err("expected tampered asset to fail");
+        assert!(format!("{:#}", err).contains("has digest"), "{:#}", err);
+
+        copier(true).copy_all(&parcels, &dst).await?;
+        assert!(!dst.join("module.wasm").exists());
+
+        Ok(())
+    }
+}
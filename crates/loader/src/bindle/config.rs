@@ -1,6 +1,17 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Invoice annotation holding the Spin manifest version the application was
+/// published from. The application information lives in the invoice, next to
+/// the bindle name, description and authors, while the manifest parcel holds
+/// the configuration needed to run the application.
+pub const SPIN_VERSION_ANNOTATION: &str = "fermyon.spin.spin_version";
+/// Invoice annotation holding the version of the application, without the
+/// build metadata of the bindle ID.
+pub const VERSION_ANNOTATION: &str = "fermyon.spin.version";
+/// Invoice annotation holding the namespace of the application.
+pub const NAMESPACE_ANNOTATION: &str = "fermyon.spin.namespace";
+
 /// Application configuration file format.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
@@ -8,6 +19,10 @@ pub struct RawAppManifest {
     /// The application trigger.
     pub trigger: spin_config::ApplicationTrigger,
 
+    /// Key-value stores available to the application components.
+    #[serde(rename = "keyValueStore")]
+    pub key_value_stores: Option<Vec<spin_config::KeyValueStoreConfig>>,
+
     /// Configuration for the application components.
     #[serde(rename = "component")]
     pub components: Vec<RawComponentManifest>,
@@ -37,6 +52,12 @@ pub struct RawWasmConfig {
     pub files: Option<String>,
     /// Optional list of HTTP hosts the component is allowed to connect.
     pub allowed_http_hosts: Option<Vec<String>>,
+    /// Optional list of Redis server addresses the component is allowed to connect.
+    pub allowed_redis_addresses: Option<Vec<String>>,
+    /// Names of the application key-value stores the component is allowed to use.
+    pub key_value_stores: Option<Vec<String>>,
+    /// Limits of the outbound HTTP requests of the component.
+    pub outbound_http: Option<spin_config::OutboundHttpConfig>,
     /// Environment variables to be mapped inside the Wasm module at runtime.
     pub environment: Option<HashMap<String, String>>,
 }
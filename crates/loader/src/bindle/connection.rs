@@ -0,0 +1,294 @@
+#![deny(missing_docs)]
+
+use crate::bindle::BindleTokenManager;
+use anyhow::{anyhow, bail, Context, Result};
+use bindle::client::{tokens::NoToken, Client, ClientBuilder};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use tracing::log;
+
+/// Environment variable for the Bindle username.
+pub const BINDLE_USERNAME_ENV: &str = "BINDLE_USERNAME";
+/// Environment variable for the Bindle password.
+pub const BINDLE_PASSWORD_ENV: &str = "BINDLE_PASSWORD";
+/// Environment variable for the Bindle bearer token.
+pub const BINDLE_TOKEN_ENV: &str = "BINDLE_TOKEN";
+/// Environment variable overriding the location of the Bindle credentials file.
+pub const BINDLE_CREDENTIALS_FILE_ENV: &str = "SPIN_BINDLE_CREDENTIALS";
+
+/// Credentials given explicitly for a Bindle server, for example on the command line.
+#[derive(Clone, Default)]
+pub struct BindleAuth {
+    /// Username for HTTP basic authentication.
+    pub username: Option<String>,
+    /// Password for HTTP basic authentication.
+    pub password: Option<String>,
+    /// Token for bearer authentication.
+    pub token: Option<String>,
+}
+
+impl BindleAuth {
+    /// Reads the credentials from the `BINDLE_USERNAME`, `BINDLE_PASSWORD`
+    /// and `BINDLE_TOKEN` environment variables.
+    pub fn from_env() -> Self {
+        Self {
+            username: std::env::var(BINDLE_USERNAME_ENV).ok(),
+            password: std::env::var(BINDLE_PASSWORD_ENV).ok(),
+            token: std::env::var(BINDLE_TOKEN_ENV).ok(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.username.is_none() && self.password.is_none() && self.token.is_none()
+    }
+
+    fn token_manager(&self) -> Result<BindleTokenManager> {
+        match (&self.username, &self.password, &self.token) {
+            (None, None, None) => Ok(BindleTokenManager::NoToken(NoToken)),
+            (None, None, Some(token)) => Ok(BindleTokenManager::Bearer(token.clone())),
+            (Some(username), Some(password), None) => Ok(BindleTokenManager::HttpBasic {
+                username: username.clone(),
+                password: password.clone(),
+            }),
+            (_, _, Some(_)) => bail!("Specify either a Bindle token or a username and password"),
+            (Some(_), None, None) => bail!("A Bindle username requires a password"),
+            (None, Some(_), None) => bail!("A Bindle password requires a username"),
+        }
+    }
+}
+
+/// Everything needed to connect to a Bindle server.
+#[derive(Clone, Debug)]
+pub struct BindleConnectionInfo {
+    base_url: String,
+    allow_insecure: bool,
+    token_manager: BindleTokenManager,
+}
+
+impl BindleConnectionInfo {
+    /// Creates the connection information for a Bindle server. Explicit credentials
+    /// take precedence; otherwise the credentials file is searched for an entry
+    /// matching the server URL, and if there is none the server is accessed anonymously.
+    pub fn new(
+        base_url: impl Into<String>,
+        allow_insecure: bool,
+        auth: BindleAuth,
+    ) -> Result<Self> {
+        let base_url = base_url.into();
+        let auth = match auth.is_empty() {
+            true => credentials_from_file(&base_url)?.unwrap_or_default(),
+            false => auth,
+        };
+        let token_manager = auth
+            .token_manager()
+            .with_context(|| anyhow!("Invalid credentials for Bindle server '{}'", base_url))?;
+
+        Ok(Self {
+            base_url,
+            allow_insecure,
+            token_manager,
+        })
+    }
+
+    /// The URL of the Bindle server.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Creates a client for the Bindle server.
+    pub fn client(&self) -> Result<Client<BindleTokenManager>> {
+        ClientBuilder::default()
+            .danger_accept_invalid_certs(self.allow_insecure)
+            .build(&self.base_url, self.token_manager.clone())
+            .with_context(|| {
+                anyhow!(
+                    "Failed to create client for bindle server '{}'",
+                    self.base_url
+                )
+            })
+    }
+}
+
+/// The credentials file, listing credentials per Bindle server.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CredentialsFile {
+    #[serde(default)]
+    server: Vec<ServerCredentials>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServerCredentials {
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+    token: Option<String>,
+}
+
+fn credentials_file_path() -> Option<PathBuf> {
+    match std::env::var(BINDLE_CREDENTIALS_FILE_ENV) {
+        Ok(path) => Some(PathBuf::from(path)),
+        Err(_) => dirs::config_dir().map(|d| d.join("spin").join("bindle-credentials.toml")),
+    }
+}
+
+fn credentials_from_file(base_url: &str) -> Result<Option<BindleAuth>> {
+    match credentials_file_path() {
+        Some(path) if path.is_file() => credentials_for(&path, base_url),
+        _ => Ok(None),
+    }
+}
+
+fn credentials_for(path: &Path, base_url: &str) -> Result<Option<BindleAuth>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| anyhow!("Cannot read Bindle credentials file {}", path.display()))?;
+    let file: CredentialsFile = toml::from_str(&text)
+        .with_context(|| anyhow!("Invalid Bindle credentials file {}", path.display()))?;
+
+    let base_url = base_url.trim_end_matches('/');
+    let auth = file
+        .server
+        .into_iter()
+        .find(|s| s.url.trim_end_matches('/') == base_url)
+        .map(|s| {
+            log::trace!("Using credentials for '{}' from {}", s.url, path.display());
+            BindleAuth {
+                username: s.username,
+                password: s.password,
+                token: s.token,
+            }
+        });
+    Ok(auth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindle::BindleReader;
+    use hyper::{
+        header::AUTHORIZATION,
+        service::{make_service_fn, service_fn},
+        Body, Request, Response, Server, StatusCode,
+    };
+    use std::{convert::Infallible, net::SocketAddr};
+
+    const TEST_INVOICE: &str = "tests/standalone-bindle/d04336f0b275c5a9f91872542a028bbfb90755535175cb69cb246a4783957512/invoice.toml";
+    const TEST_BINDLE_ID: &str = "spin-module-test/1.0.0";
+
+    /// Serves the test invoice only to requests with the expected authorization header.
+    fn serve_bindle(expected_auth: &'static str) -> SocketAddr {
+        let make_svc = make_service_fn(move |_| async move {
+            Ok::<_, Infallible>(service_fn(move |req: Request<Body>| async move {
+                let authorized = req
+                    .headers()
+                    .get(AUTHORIZATION)
+                    .map(|h| h == expected_auth)
+                    .unwrap_or(false);
+                let res = match authorized {
+                    true => Response::new(Body::from(std::fs::read(TEST_INVOICE).unwrap())),
+                    false => Response::builder()
+                        .status(StatusCode::UNAUTHORIZED)
+                        .body(Body::empty())
+                        .unwrap(),
+                };
+                Ok::<_, Infallible>(res)
+            }))
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_svc);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        addr
+    }
+
+    async fn get_invoice(addr: SocketAddr, auth: BindleAuth) -> Result<bindle::Invoice> {
+        let url = format!("http://{}/v1", addr);
+        let client = BindleConnectionInfo::new(url, false, auth)?.client()?;
+        BindleReader::remote(&client, &TEST_BINDLE_ID.parse()?)
+            .get_invoice()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_basic_auth() -> Result<()> {
+        // "spin:hunter2", base64-encoded.
+        let addr = serve_bindle("Basic c3BpbjpodW50ZXIy");
+
+        let auth = BindleAuth {
+            username: Some("spin".to_owned()),
+            password: Some("hunter2".to_owned()),
+            token: None,
+        };
+        let invoice = get_invoice(addr, auth).await?;
+        assert_eq!(invoice.bindle.id.to_string(), TEST_BINDLE_ID);
+
+        let wrong = BindleAuth {
+            username: Some("spin".to_owned()),
+            password: Some("wrong".to_owned()),
+            token: None,
+        };
+        assert!(get_invoice(addr, wrong).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bearer_auth() -> Result<()> {
+        let addr = serve_bindle("Bearer s3cr3t");
+
+        let auth = BindleAuth {
+            token: Some("s3cr3t".to_owned()),
+            ..Default::default()
+        };
+        let invoice = get_invoice(addr, auth).await?;
+        assert_eq!(invoice.bindle.id.to_string(), TEST_BINDLE_ID);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_credentials() {
+        let username_only = BindleAuth {
+            username: Some("spin".to_owned()),
+            ..Default::default()
+        };
+        assert!(BindleConnectionInfo::new("http://localhost/v1", false, username_only).is_err());
+
+        let both = BindleAuth {
+            username: Some("spin".to_owned()),
+            password: Some("hunter2".to_owned()),
+            token: Some("s3cr3t".to_owned()),
+        };
+        assert!(BindleConnectionInfo::new("http://localhost/v1", false, both).is_err());
+    }
+
+    #[test]
+    fn test_credentials_file() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("bindle-credentials.toml");
+        std::fs::write(
+            &path,
+            r#"
+            [[server]]
+            url = "https://bindle.example.com/v1/"
+            username = "spin"
+            password = "hunter2"
+
+            [[server]]
+            url = "https://other.example.com/v1"
+            token = "s3cr3t"
+            "#,
+        )?;
+
+        let auth = credentials_for(&path, "https://bindle.example.com/v1")?.unwrap();
+        assert_eq!(auth.username.as_deref(), Some("spin"));
+        assert_eq!(auth.password.as_deref(), Some("hunter2"));
+
+        let auth = credentials_for(&path, "https://other.example.com/v1")?.unwrap();
+        assert_eq!(auth.token.as_deref(), Some("s3cr3t"));
+
+        assert!(credentials_for(&path, "https://unknown.example.com/v1")?.is_none());
+
+        Ok(())
+    }
+}
@@ -6,43 +6,83 @@
 mod assets;
 /// Configuration representation for a Spin application in Bindle.
 pub mod config;
+/// Connection and authentication settings for Bindle servers.
+mod connection;
+/// Verification of bindle invoice signatures.
+mod signature;
 /// Bindle helper functions.
 mod utils;
 
-use crate::bindle::{
-    config::{RawAppManifest, RawComponentManifest},
-    utils::{find_manifest, BindleReader},
+use crate::{
+    bindle::{
+        config::{
+            RawAppManifest, RawComponentManifest, NAMESPACE_ANNOTATION, SPIN_VERSION_ANNOTATION,
+            VERSION_ANNOTATION,
+        },
+        utils::find_manifest,
+    },
+    key_value,
 };
-use anyhow::{anyhow, Context, Result};
-use bindle::{
-    client::{tokens::NoToken, Client},
-    Invoice,
+use anyhow::{anyhow, bail, Context, Result};
+use bindle::Invoice;
+pub use assets::ALLOW_UNVERIFIED_ASSETS_ENV;
+pub use connection::{
+    BindleAuth, BindleConnectionInfo, BINDLE_CREDENTIALS_FILE_ENV, BINDLE_PASSWORD_ENV,
+    BINDLE_TOKEN_ENV, BINDLE_USERNAME_ENV,
 };
 use futures::future;
+pub use signature::SignaturePolicy;
 use spin_config::{
-    Application, ApplicationInformation, ApplicationOrigin, CoreComponent, ModuleSource,
-    SpinVersion, WasmConfig,
+    parse_allowed_network, AllowedHttpHost, Application, ApplicationInformation, ApplicationOrigin,
+    CoreComponent, ModuleSource, SpinVersion, WasmConfig,
 };
 use std::path::Path;
 use tracing::log;
-pub use utils::{BindleTokenManager, SPIN_MANIFEST_MEDIA_TYPE};
+pub(crate) use utils::BindleReader;
+pub use utils::{verify_parcel, BindleTokenManager, SPIN_MANIFEST_MEDIA_TYPE};
 
-/// Given a Bindle server URL and reference, pull it, expand its assets locally, and get a
-/// prepared application configuration consumable by a Spin execution context.
+/// Given a Bindle server connection and reference, pull it, check its signature against
+/// the policy, expand its assets locally, and get a prepared application configuration
+/// consumable by a Spin execution context.
 /// If a directory is provided, use it as the base directory to expand the assets,
 /// otherwise create a new temporary directory.
 pub async fn from_bindle(
     id: &str,
-    url: &str,
+    connection: &BindleConnectionInfo,
+    policy: &SignaturePolicy,
+    base_dst: impl AsRef<Path>,
+) -> Result<Application<CoreComponent>> {
+    let client = connection.client()?;
+    // Parcels are shared through the local cache, so they are only downloaded once.
+    let cache_dir = crate::cache::parcel_cache_dir(&base_dst);
+    let reader = BindleReader::remote(&client, &id.parse()?).with_cache(&cache_dir);
+
+    let app = prepare(id, connection.base_url(), &reader, policy, base_dst).await?;
+    crate::cache::prune_after_load(&cache_dir).await;
+    Ok(app)
+}
+
+/// Given a standalone bindle directory, such as one written by `spin bindle prepare`,
+/// and a reference, expand its assets locally, and get a prepared application
+/// configuration consumable by a Spin execution context, without a Bindle server.
+pub async fn from_standalone_bindle(
+    id: &str,
+    dir: impl AsRef<Path>,
+    policy: &SignaturePolicy,
     base_dst: impl AsRef<Path>,
 ) -> Result<Application<CoreComponent>> {
-    // TODO
-    // Handle Bindle authentication.
-    let manager = BindleTokenManager::NoToken(NoToken);
-    let client = Client::new(url, manager)?;
-    let reader = BindleReader::remote(&client, &id.parse()?);
+    let dir = dir.as_ref();
+    let reader = BindleReader::standalone(dir, &id.parse()?)
+        .await
+        .with_context(|| {
+            anyhow!(
+                "Failed to find bindle '{}' in standalone directory '{}'",
+                id,
+                dir.display()
+            )
+        })?;
 
-    prepare(id, url, &reader, base_dst).await
+    prepare(id, &dir.display().to_string(), &reader, policy, base_dst).await
 }
 
 /// Converts a Bindle invoice into Spin configuration.
@@ -50,21 +90,31 @@ async fn prepare(
     id: &str,
     url: &str,
     reader: &BindleReader,
+    policy: &SignaturePolicy,
     base_dst: impl AsRef<Path>,
 ) -> Result<Application<CoreComponent>> {
-    // First, get the invoice from the Bindle server.
+    // First, get the invoice from the Bindle server or directory.
     let invoice = reader
         .get_invoice()
         .await
         .with_context(|| anyhow!("Failed to load invoice '{}' from '{}'", id, url))?;
+    policy.verify(&invoice).await?;
 
     // Then, reconstruct the application manifest from the parcels.
-    let raw: RawAppManifest =
-        toml::from_slice(&reader.get_parcel(&find_manifest(&invoice)?).await?)?;
+    let manifest = reader
+        .get_parcel(&find_manifest(&invoice)?)
+        .await
+        .context("Cannot get the Spin manifest from bindle")?;
+    let raw: RawAppManifest = toml::from_slice(&manifest)?;
     log::trace!("Recreated manifest from bindle: {:?}", raw);
 
-    let info = info(&raw, &invoice, url);
+    let info = info(&raw, &invoice, url)?;
     log::trace!("Application information from bindle: {:?}", info);
+    let key_value_stores = key_value::prepare_stores(
+        raw.key_value_stores.clone().unwrap_or_default(),
+        None,
+        &info.name,
+    )?;
     let component_triggers = raw
         .components
         .iter()
@@ -78,13 +128,16 @@ async fn prepare(
     )
     .await
     .into_iter()
-    .map(|x| x.expect("Cannot prepare component"))
-    .collect::<Vec<_>>();
+    .collect::<Result<Vec<_>>>()?;
+    for c in &components {
+        key_value::check_component_stores(&c.id, &c.wasm.key_value_stores, &key_value_stores)?;
+    }
 
     Ok(Application {
         info,
         components,
         component_triggers,
+        key_value_stores,
     })
 }
 
@@ -95,8 +148,21 @@ async fn core(
     reader: &BindleReader,
     base_dst: impl AsRef<Path>,
 ) -> Result<CoreComponent> {
+    let label = invoice
+        .parcel
+        .iter()
+        .flatten()
+        .map(|p| &p.label)
+        .find(|l| l.sha256 == raw.source)
+        .ok_or_else(|| {
+            anyhow!(
+                "Invoice does not contain module {} of component '{}'",
+                raw.source,
+                raw.id
+            )
+        })?;
     let bytes = reader
-        .get_parcel(&raw.source)
+        .get_parcel(label)
         .await
         .with_context(|| anyhow!("Cannot get module source from bindle"))?;
 
@@ -117,30 +183,59 @@ async fn core(
     };
     let environment = raw.wasm.environment.unwrap_or_default();
     let allowed_http_hosts = raw.wasm.allowed_http_hosts.unwrap_or_default();
+    AllowedHttpHost::parse_all(&allowed_http_hosts)
+        .with_context(|| anyhow!("Invalid allowed HTTP hosts for component '{}'", id))?;
+    let allowed_redis_addresses = raw.wasm.allowed_redis_addresses.unwrap_or_default();
+    let key_value_stores = raw.wasm.key_value_stores.unwrap_or_default();
+    let outbound_http = raw.wasm.outbound_http.unwrap_or_default();
+    for network in &outbound_http.allowed_networks {
+        parse_allowed_network(network)
+            .with_context(|| anyhow!("Invalid allowed network for component '{}'", id))?;
+    }
     let wasm = WasmConfig {
         environment,
         mounts,
         allowed_http_hosts,
+        allowed_redis_addresses,
+        key_value_stores,
+        outbound_http,
     };
     Ok(CoreComponent { source, id, wasm })
 }
 
 /// Converts the raw application manifest from the bindle invoice into the
-/// standard application configuration.
-fn info(raw: &RawAppManifest, invoice: &Invoice, url: &str) -> ApplicationInformation {
-    ApplicationInformation {
-        // TODO
-        // Handle API version and namespace.
-        spin_version: SpinVersion::V1,
+/// standard application configuration. Information missing from the invoice
+/// annotations, as in bindles published by older versions of Spin, is taken
+/// from the bindle ID.
+fn info(raw: &RawAppManifest, invoice: &Invoice, url: &str) -> Result<ApplicationInformation> {
+    let annotation = |key: &str| {
+        invoice
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(key))
+            .cloned()
+    };
+    let spin_version = match annotation(SPIN_VERSION_ANNOTATION).as_deref() {
+        None | Some("1") => SpinVersion::V1,
+        Some(v) => bail!(
+            "Bindle '{}' has unsupported Spin manifest version '{}'",
+            invoice.bindle.id,
+            v
+        ),
+    };
+
+    Ok(ApplicationInformation {
+        spin_version,
         name: invoice.bindle.id.name().to_string(),
-        version: invoice.bindle.id.version_string(),
+        version: annotation(VERSION_ANNOTATION)
+            .unwrap_or_else(|| invoice.bindle.id.version_string()),
         description: invoice.bindle.description.clone(),
         authors: invoice.bindle.authors.clone().unwrap_or_default(),
         trigger: raw.trigger.clone(),
-        namespace: None,
+        namespace: annotation(NAMESPACE_ANNOTATION),
         origin: ApplicationOrigin::Bindle {
             id: invoice.bindle.id.to_string(),
             server: url.to_string(),
         },
-    }
+    })
 }
@@ -0,0 +1,215 @@
+#![deny(missing_docs)]
+
+use anyhow::{anyhow, bail, Context, Result};
+use bindle::{
+    invoice::{signature::KeyRing, VerificationStrategy},
+    Invoice,
+};
+use std::path::{Path, PathBuf};
+use tracing::log;
+
+/// How the signatures of bindle invoices are checked before an application is loaded.
+#[derive(Clone, Debug, Default)]
+pub struct SignaturePolicy {
+    require_signature: bool,
+    keyring: Option<KeyRing>,
+    /// The default trusted keys file, only read when an invoice is verified,
+    /// so that applications without bindles do not depend on it.
+    default_trusted_keys_file: Option<PathBuf>,
+}
+
+impl SignaturePolicy {
+    /// Creates a signature policy. The trusted keys are read from the given
+    /// keyring file or, if none is given, from `spin/trusted-keys.toml` in the
+    /// user configuration directory, if it exists, when invoices are verified.
+    /// Requiring signatures without any trusted keys is an error.
+    pub async fn new(require_signature: bool, trusted_keys_file: Option<&Path>) -> Result<Self> {
+        let (keyring, default_trusted_keys_file) = match trusted_keys_file {
+            Some(path) => (Some(load_keyring(path).await?), None),
+            None => (None, default_trusted_keys_file().filter(|p| p.is_file())),
+        };
+        if require_signature && keyring.is_none() && default_trusted_keys_file.is_none() {
+            bail!("Requiring bindle signatures needs a trusted keys file");
+        }
+
+        Ok(Self {
+            require_signature,
+            keyring,
+            default_trusted_keys_file,
+        })
+    }
+
+    /// Creates a policy that requires invoices to be signed by one of the trusted keys.
+    pub fn require(keyring: KeyRing) -> Self {
+        Self {
+            require_signature: true,
+            keyring: Some(keyring),
+            default_trusted_keys_file: None,
+        }
+    }
+
+    /// Returns whether unsigned invoices are rejected.
+    pub(crate) fn requires_signature(&self) -> bool {
+        self.require_signature
+    }
+
+    /// Checks the invoice against the policy. Signed invoices are always verified
+    /// when there are trusted keys; unsigned invoices are rejected only when
+    /// signatures are required.
+    pub(crate) async fn verify(&self, invoice: &Invoice) -> Result<()> {
+        let id = &invoice.bindle.id;
+        let signed = invoice
+            .signature
+            .as_ref()
+            .map(|s| !s.is_empty())
+            .unwrap_or(false);
+
+        let default_keyring = match (signed, &self.default_trusted_keys_file) {
+            (true, Some(path)) => self.load_default_keyring(path).await?,
+            _ => None,
+        };
+        match (signed, self.keyring.as_ref().or(default_keyring.as_ref())) {
+            (false, _) if self.require_signature => bail!("Bindle '{}' is not signed", id),
+            (false, _) => Ok(()),
+            (true, Some(keyring)) => {
+                VerificationStrategy::CreativeIntegrity
+                    .verify(invoice.clone(), keyring)
+                    .map_err(|e| anyhow!("{}", e))
+                    .with_context(|| {
+                        anyhow!("Failed to verify the signature of bindle '{}'", id)
+                    })?;
+                log::trace!("Verified the signature of bindle '{}'", id);
+                Ok(())
+            }
+            (true, None) => {
+                log::warn!(
+                    "Bindle '{}' is signed, but there are no trusted keys to verify it with",
+                    id
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+impl SignaturePolicy {
+    /// Reads the default trusted keys file. When signatures are not required,
+    /// an invalid file only means there are no trusted keys.
+    async fn load_default_keyring(&self, path: &Path) -> Result<Option<KeyRing>> {
+        match load_keyring(path).await {
+            Ok(keyring) => Ok(Some(keyring)),
+            Err(e) if !self.require_signature => {
+                log::warn!("Ignoring the default trusted keys: {:#}", e);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn default_trusted_keys_file() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("spin").join("trusted-keys.toml"))
+}
+
+async fn load_keyring(path: &Path) -> Result<KeyRing> {
+    let bytes = tokio::fs::read(path)
+        .await
+        .with_context(|| anyhow!("Cannot read trusted keys file {}", path.display()))?;
+    toml::from_slice(&bytes)
+        .with_context(|| anyhow!("Invalid trusted keys file {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bindle::invoice::{
+        sign,
+        signature::{KeyEntry, SecretKeyEntry, SignatureRole},
+        Signed,
+    };
+    use std::convert::TryFrom;
+
+    const TEST_INVOICE: &str = "tests/standalone-bindle/d04336f0b275c5a9f91872542a028bbfb90755535175cb69cb246a4783957512/invoice.toml";
+
+    fn test_invoice() -> Invoice {
+        toml::from_slice(&std::fs::read(TEST_INVOICE).unwrap()).unwrap()
+    }
+
+    fn test_key(label: &str) -> (SecretKeyEntry, KeyRing) {
+        let secret = SecretKeyEntry::new(label.to_owned(), vec![SignatureRole::Creator]);
+        let public = KeyEntry::try_from(secret.clone()).unwrap();
+        (secret, KeyRing::new(vec![public]))
+    }
+
+    fn signed(invoice: Invoice, secret: &SecretKeyEntry) -> Invoice {
+        sign(invoice, vec![(SignatureRole::Creator, secret)])
+            .unwrap()
+            .signed()
+    }
+
+    #[tokio::test]
+    async fn test_signed_invoice_is_verified() {
+        let (secret, keyring) = test_key("spin-test");
+        let invoice = signed(test_invoice(), &secret);
+
+        assert!(SignaturePolicy::require(keyring)
+            .verify(&invoice)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_tampered_invoice_is_rejected() {
+        let (secret, keyring) = test_key("spin-test");
+        let mut invoice = signed(test_invoice(), &secret);
+        invoice.parcel.as_mut().unwrap()[0].label.sha256 = "0".repeat(64);
+
+        assert!(SignaturePolicy::require(keyring)
+            .verify(&invoice)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_untrusted_signature_is_rejected() {
+        let (secret, _) = test_key("spin-test");
+        let (_, other_keyring) = test_key("someone-else");
+        let invoice = signed(test_invoice(), &secret);
+
+        assert!(SignaturePolicy::require(other_keyring)
+            .verify(&invoice)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_invoice() {
+        let (_, keyring) = test_key("spin-test");
+
+        assert!(SignaturePolicy::default()
+            .verify(&test_invoice())
+            .await
+            .is_ok());
+        assert!(SignaturePolicy::require(keyring)
+            .verify(&test_invoice())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_default_keyring() {
+        let (secret, _) = test_key("spin-test");
+        let invoice = signed(test_invoice(), &secret);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trusted-keys.toml");
+        std::fs::write(&path, "not a keyring").unwrap();
+
+        let policy = |require_signature| SignaturePolicy {
+            require_signature,
+            keyring: None,
+            default_trusted_keys_file: Some(path.clone()),
+        };
+        assert!(policy(false).verify(&invoice).await.is_ok());
+        assert!(policy(true).verify(&invoice).await.is_err());
+    }
+}
@@ -1,5 +1,6 @@
 #![deny(missing_docs)]
 
+use crate::cache::{read_cached, write_cached};
 use anyhow::{anyhow, bail, Context, Result};
 use async_trait::async_trait;
 use bindle::{
@@ -13,8 +14,14 @@ use bindle::{
 };
 use itertools::Itertools;
 use reqwest::RequestBuilder;
-use std::{fmt::Debug, path::Path, sync::Arc};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use tokio::fs;
+use tracing::log;
 
 static EMPTY: &Vec<bindle::Parcel> = &vec![];
 
@@ -24,7 +31,7 @@ static EMPTY: &Vec<bindle::Parcel> = &vec![];
 /// The media type of a `spin.toml` parcel as part of a bindle.
 pub const SPIN_MANIFEST_MEDIA_TYPE: &str = "application/vnd.fermyon.spin+toml";
 
-pub(crate) fn find_manifest(inv: &Invoice) -> Result<String> {
+pub(crate) fn find_manifest(inv: &Invoice) -> Result<Label> {
     let parcels = inv
         .parcel
         .as_ref()
@@ -41,7 +48,7 @@ pub(crate) fn find_manifest(inv: &Invoice) -> Result<String> {
 
     match parcels.len() {
         0 => bail!("Invoice does not contain a Spin manifest"),
-        1 => Ok(parcels[0].sha256.clone()),
+        1 => Ok(parcels[0].clone()),
         _ => bail!("Invoice contains multiple Spin manifests"),
     }
 }
@@ -99,11 +106,44 @@ trigger     = "http"
 
 */
 
+/// Checks that the content of a parcel matches the size and SHA256 digest
+/// recorded in its invoice label.
+pub fn verify_parcel(label: &Label, bytes: &[u8]) -> Result<()> {
+    if bytes.len() as u64 != label.size {
+        bail!(
+            "Parcel '{}' ({}) has size {} bytes, expected {} bytes",
+            label.name,
+            label.sha256,
+            bytes.len(),
+            label.size
+        );
+    }
+    let digest = format!("{:x}", Sha256::digest(bytes));
+    if !digest.eq_ignore_ascii_case(&label.sha256) {
+        bail!(
+            "Parcel '{}' has digest {}, expected {}",
+            label.name,
+            digest,
+            label.sha256
+        );
+    }
+    Ok(())
+}
+
 /// Any kind of Bindle authentication.
 #[derive(Clone)]
 pub enum BindleTokenManager {
     /// Anonymous authentication
     NoToken(NoToken),
+    /// HTTP basic authentication with a username and password
+    HttpBasic {
+        /// The username
+        username: String,
+        /// The password
+        password: String,
+    },
+    /// Bearer token authentication
+    Bearer(String),
 }
 
 #[async_trait]
@@ -111,14 +151,24 @@ impl TokenManager for BindleTokenManager {
     async fn apply_auth_header(&self, builder: RequestBuilder) -> client::Result<RequestBuilder> {
         match self {
             Self::NoToken(t) => t.apply_auth_header(builder).await,
+            Self::HttpBasic { username, password } => {
+                Ok(builder.basic_auth(username, Some(password)))
+            }
+            Self::Bearer(token) => Ok(builder.bearer_auth(token)),
         }
     }
 }
 
 impl Debug for BindleTokenManager {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print the credentials themselves.
         match self {
             Self::NoToken(_) => f.debug_tuple("NoToken").finish(),
+            Self::HttpBasic { username, .. } => f
+                .debug_struct("HttpBasic")
+                .field("username", username)
+                .finish_non_exhaustive(),
+            Self::Bearer(_) => f.debug_tuple("Bearer").finish(),
         }
     }
 }
@@ -127,11 +177,34 @@ impl Debug for BindleTokenManager {
 #[derive(Clone, Debug)]
 pub(crate) struct BindleReader {
     inner: BindleReaderInner,
+    cache_dir: Option<PathBuf>,
 }
 
 impl BindleReader {
-    /// Gets the content of a parcel from the bindle source.
-    pub(crate) async fn get_parcel(&self, id: &str) -> Result<Vec<u8>> {
+    /// Gets the content of a parcel from the bindle source, and verifies it
+    /// against the size and digest in the parcel label.
+    /// If the reader has a cache, the parcel is read from the cache when present,
+    /// and saved to it otherwise.
+    pub(crate) async fn get_parcel(&self, label: &Label) -> Result<Vec<u8>> {
+        if let Some(cache_dir) = &self.cache_dir {
+            if let Some(bytes) = read_cached(&label.sha256, cache_dir).await? {
+                verify_parcel(label, &bytes)?;
+                return Ok(bytes);
+            }
+        }
+
+        let bytes = self.read_parcel(&label.sha256).await?;
+        verify_parcel(label, &bytes)?;
+
+        if let Some(cache_dir) = &self.cache_dir {
+            if let Err(e) = write_cached(&label.sha256, &bytes, cache_dir).await {
+                log::warn!("Failed to cache parcel {}: {:?}", label.sha256, e);
+            }
+        }
+        Ok(bytes)
+    }
+
+    async fn read_parcel(&self, id: &str) -> Result<Vec<u8>> {
         match &self.inner {
             BindleReaderInner::Remote(c, bindle_id) => c
                 .get_parcel(bindle_id, id)
@@ -179,14 +252,23 @@ impl BindleReader {
     pub(crate) fn remote(c: &Client<BindleTokenManager>, id: &Id) -> Self {
         Self {
             inner: BindleReaderInner::Remote(c.clone(), id.clone()),
+            cache_dir: None,
+        }
+    }
+
+    /// Uses the given directory as a content-addressed cache for parcels.
+    pub(crate) fn with_cache(self, cache_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            cache_dir: Some(cache_dir.into()),
+            ..self
         }
     }
 
-    #[allow(dead_code)]
     pub(crate) async fn standalone(base_path: impl AsRef<Path>, id: &Id) -> Result<Self> {
         let s = StandaloneRead::new(&base_path, id).await?;
         Ok(Self {
             inner: BindleReaderInner::Standalone(Arc::new(s)),
+            cache_dir: None,
         })
     }
 }
@@ -205,3 +287,100 @@ impl Debug for BindleReaderInner {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_BINDLE_DIR: &str = "tests/standalone-bindle";
+    const TEST_BINDLE_ID: &str = "spin-module-test/1.0.0";
+
+    async fn module_label(reader: &BindleReader) -> Result<Label> {
+        let invoice = reader.get_invoice().await?;
+        Ok(invoice
+            .parcel
+            .unwrap()
+            .into_iter()
+            .find(|p| p.label.name == "module.wasm")
+            .unwrap()
+            .label)
+    }
+
+    #[tokio::test]
+    async fn test_parcel_is_verified() -> Result<()> {
+        let reader = BindleReader::standalone(TEST_BINDLE_DIR, &TEST_BINDLE_ID.parse()?).await?;
+        let label = module_label(&reader).await?;
+        let bytes = reader.get_parcel(&label).await?;
+
+        let mut wrong_size = label.clone();
+        wrong_size.size += 1;
+        assert!(verify_parcel(&wrong_size, &bytes).is_err());
+
+        let mut tampered = bytes.clone();
+        tampered[0] ^= 0xff;
+        assert!(verify_parcel(&label, &tampered).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tampered_standalone_parcel_is_rejected() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        fs_extra::dir::copy(
+            TEST_BINDLE_DIR,
+            temp_dir.path(),
+            &fs_extra::dir::CopyOptions {
+                content_only: true,
+                ..Default::default()
+            },
+        )?;
+        let id: Id = TEST_BINDLE_ID.parse()?;
+        let reader = BindleReader::standalone(temp_dir.path(), &id).await?;
+        let label = module_label(&reader).await?;
+
+        let parcel_file = temp_dir
+            .path()
+            .join(id.sha())
+            .join("parcels")
+            .join(format!("{}.dat", label.sha256));
+        let mut bytes = std::fs::read(&parcel_file)?;
+        bytes[0] ^= 0xff;
+        std::fs::write(&parcel_file, bytes)?;
+
+        let err = reader
+            .get_parcel(&label)
+            .await
+            .expect_err("expected tampered parcel to fail");
+        assert!(err.to_string().contains("has digest"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_parcels_are_cached() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let bindle_dir = temp_dir.path().join("bindle");
+        let cache_dir = temp_dir.path().join("cache");
+        fs_extra::dir::copy(
+            TEST_BINDLE_DIR,
+            &bindle_dir,
+            &fs_extra::dir::CopyOptions {
+                content_only: true,
+                ..Default::default()
+            },
+        )?;
+        let reader = BindleReader::standalone(&bindle_dir, &TEST_BINDLE_ID.parse()?)
+            .await?
+            .with_cache(&cache_dir);
+        let label = module_label(&reader).await?;
+
+        let bytes = reader.get_parcel(&label).await?;
+        assert!(cache_dir.join(format!("{}.dat", label.sha256)).is_file());
+
+        // Once cached, the parcel no longer needs to be read from the bindle.
+        std::fs::remove_dir_all(&bindle_dir)?;
+        assert_eq!(reader.get_parcel(&label).await?, bytes);
+
+        Ok(())
+    }
+}
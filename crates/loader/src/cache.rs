@@ -0,0 +1,299 @@
+//! Content-addressed cache for parcels and modules fetched from remote sources.
+//!
+//! Parcels are stored as `<sha256>.dat` and shared by all applications and
+//! versions that reference the same content. The cache is kept under a size
+//! limit by evicting the least recently used parcels once an application has
+//! been loaded.
+
+#![deny(missing_docs)]
+
+use anyhow::{anyhow, Context, Result};
+use sha2::{Digest, Sha256};
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tokio::fs;
+use tracing::log;
+
+/// Environment variable setting the maximum size of the parcel cache, in bytes.
+pub const PARCEL_CACHE_MAX_SIZE_ENV: &str = "SPIN_PARCEL_CACHE_MAX_SIZE";
+
+/// The default maximum size of the parcel cache: 1 GiB.
+pub const DEFAULT_PARCEL_CACHE_MAX_SIZE: u64 = 1024 * 1024 * 1024;
+
+const PARCEL_EXTENSION: &str = "dat";
+
+/// A parcel stored in the cache.
+#[derive(Clone, Debug)]
+pub struct CacheEntry {
+    /// The SHA256 digest of the parcel.
+    pub digest: String,
+    /// The size of the parcel, in bytes.
+    pub size: u64,
+    /// When the parcel was last written or read.
+    pub last_used: SystemTime,
+}
+
+/// Returns the default directory of the parcel cache, `spin/parcels` in the
+/// user cache directory.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|d| d.join("spin").join("parcels"))
+}
+
+/// Returns the directory in which remote parcels and modules are cached, keyed
+/// by their SHA256 digest.
+pub(crate) fn parcel_cache_dir(base_dst: impl AsRef<Path>) -> PathBuf {
+    default_cache_dir().unwrap_or_else(|| base_dst.as_ref().join("parcels"))
+}
+
+/// Returns the maximum size of the parcel cache, from the environment or the default.
+pub fn max_cache_size() -> Result<u64> {
+    match std::env::var(PARCEL_CACHE_MAX_SIZE_ENV) {
+        Ok(size) => size.parse().with_context(|| {
+            anyhow!(
+                "Invalid value '{}' for {}: expected a size in bytes",
+                size,
+                PARCEL_CACHE_MAX_SIZE_ENV
+            )
+        }),
+        Err(_) => Ok(DEFAULT_PARCEL_CACHE_MAX_SIZE),
+    }
+}
+
+/// Reads a parcel from the cache, if present and intact.
+pub(crate) async fn read_cached(
+    digest: &str,
+    cache_dir: impl AsRef<Path>,
+) -> Result<Option<Vec<u8>>> {
+    let path = parcel_path(&cache_dir, digest);
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let bytes = fs::read(&path)
+        .await
+        .with_context(|| anyhow!("Failed to read cached parcel {}", path.display()))?;
+    if !sha256_digest(&bytes).eq_ignore_ascii_case(digest) {
+        log::warn!("Ignoring corrupted cached parcel {}", path.display());
+        return Ok(None);
+    }
+
+    // Record the use, so that pruning evicts the least recently used parcels first.
+    if let Err(e) = touch(&path) {
+        log::debug!("Failed to update cached parcel {}: {}", path.display(), e);
+    }
+
+    log::trace!("Using cached parcel {}", path.display());
+    Ok(Some(bytes))
+}
+
+/// Writes a parcel to the cache.
+pub(crate) async fn write_cached(
+    digest: &str,
+    bytes: &[u8],
+    cache_dir: impl AsRef<Path>,
+) -> Result<()> {
+    let cache_dir = cache_dir.as_ref();
+    fs::create_dir_all(cache_dir)
+        .await
+        .with_context(|| anyhow!("Failed to create parcel cache {}", cache_dir.display()))?;
+
+    // Write to a temporary file first, so concurrent loads never see a partial parcel.
+    let path = parcel_path(cache_dir, digest);
+    let temp = tempfile::NamedTempFile::new_in(cache_dir)?;
+    fs::write(temp.path(), bytes).await?;
+    temp.persist(&path)
+        .with_context(|| anyhow!("Failed to save parcel to cache {}", path.display()))?;
+
+    Ok(())
+}
+
+/// Prunes the cache to its maximum size once an application has been loaded.
+/// A cache that cannot be pruned, or an invalid size limit, is not a reason to
+/// fail loading the application, so failures are only logged.
+pub(crate) async fn prune_after_load(cache_dir: impl AsRef<Path>) {
+    let cache_dir = cache_dir.as_ref();
+    let max_size = max_cache_size().unwrap_or_else(|e| {
+        log::warn!("{:#}; using the default limit", e);
+        DEFAULT_PARCEL_CACHE_MAX_SIZE
+    });
+    if let Err(e) = prune(cache_dir, max_size).await {
+        log::warn!(
+            "Failed to prune parcel cache {}: {:?}",
+            cache_dir.display(),
+            e
+        );
+    }
+}
+
+/// Lists the parcels in the cache, least recently used first.
+pub async fn entries(cache_dir: impl AsRef<Path>) -> Result<Vec<CacheEntry>> {
+    let cache_dir = cache_dir.as_ref();
+    if !cache_dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut entries = vec![];
+    let mut dir = fs::read_dir(cache_dir)
+        .await
+        .with_context(|| anyhow!("Failed to read parcel cache {}", cache_dir.display()))?;
+    while let Some(entry) = dir.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(PARCEL_EXTENSION) {
+            continue;
+        }
+        let digest = match path.file_stem().and_then(|s| s.to_str()) {
+            Some(digest) => digest.to_owned(),
+            None => continue,
+        };
+        let metadata = entry.metadata().await?;
+        if !metadata.is_file() {
+            continue;
+        }
+        entries.push(CacheEntry {
+            digest,
+            size: metadata.len(),
+            last_used: metadata.modified()?,
+        });
+    }
+
+    entries.sort_by_key(|e| e.last_used);
+    Ok(entries)
+}
+
+/// Removes the least recently used parcels until the cache is no larger than
+/// the given size, and returns the removed parcels.
+pub async fn prune(cache_dir: impl AsRef<Path>, max_size: u64) -> Result<Vec<CacheEntry>> {
+    let cache_dir = cache_dir.as_ref();
+    let entries = entries(cache_dir).await?;
+    let mut size: u64 = entries.iter().map(|e| e.size).sum();
+
+    let mut removed = vec![];
+    for entry in entries {
+        if size <= max_size {
+            break;
+        }
+        let path = parcel_path(cache_dir, &entry.digest);
+        log::trace!("Evicting cached parcel {}", path.display());
+        fs::remove_file(&path)
+            .await
+            .with_context(|| anyhow!("Failed to remove cached parcel {}", path.display()))?;
+        size -= entry.size;
+        removed.push(entry);
+    }
+
+    Ok(removed)
+}
+
+/// Removes all parcels from the cache, and returns the removed parcels.
+pub async fn clear(cache_dir: impl AsRef<Path>) -> Result<Vec<CacheEntry>> {
+    prune(cache_dir, 0).await
+}
+
+/// Returns the hex-encoded SHA256 digest of the bytes.
+pub(crate) fn sha256_digest(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Digests are hex-encoded, and parcels are stored under the lowercase encoding.
+fn parcel_path(cache_dir: impl AsRef<Path>, digest: &str) -> PathBuf {
+    cache_dir.as_ref().join(format!(
+        "{}.{}",
+        digest.to_ascii_lowercase(),
+        PARCEL_EXTENSION
+    ))
+}
+
+fn touch(path: &Path) -> std::io::Result<()> {
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(path)?
+        .set_modified(SystemTime::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn cache_parcel(cache_dir: &Path, content: &[u8], age_secs: u64) -> Result<String> {
+        let digest = sha256_digest(content);
+        write_cached(&digest, content, cache_dir).await?;
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(parcel_path(cache_dir, &digest))?
+            .set_modified(SystemTime::now() - Duration::from_secs(age_secs))?;
+        Ok(digest)
+    }
+
+    #[tokio::test]
+    async fn test_prune_evicts_least_recently_used() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let cache_dir = temp_dir.path();
+
+        let old = cache_parcel(cache_dir, b"old parcel", 300).await?;
+        let used = cache_parcel(cache_dir, b"used parcel", 200).await?;
+        let new = cache_parcel(cache_dir, b"new parcel", 100).await?;
+
+        // Reading a parcel makes it the most recently used.
+        assert!(read_cached(&used, cache_dir).await?.is_some());
+        let digests =
+            |entries: Vec<CacheEntry>| entries.into_iter().map(|e| e.digest).collect::<Vec<_>>();
+        assert_eq!(
+            digests(entries(cache_dir).await?),
+            vec![old.clone(), new.clone(), used.clone()]
+        );
+
+        // Keep room for the two most recently used parcels only.
+        let removed = prune(cache_dir, 21).await?;
+        assert_eq!(digests(removed), vec![old.clone()]);
+        assert!(read_cached(&old, cache_dir).await?.is_none());
+
+        let removed = clear(cache_dir).await?;
+        assert_eq!(removed.len(), 2);
+        assert!(entries(cache_dir).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_digest_case_is_ignored() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let digest = cache_parcel(temp_dir.path(), b"parcel", 0).await?;
+
+        let bytes = read_cached(&digest.to_ascii_uppercase(), temp_dir.path()).await?;
+        assert_eq!(bytes.as_deref(), Some(&b"parcel"[..]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_uppercase_digest_is_stored_lowercase() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let digest = sha256_digest(b"parcel").to_ascii_uppercase();
+        write_cached(&digest, b"parcel", temp_dir.path()).await?;
+
+        for digest in [digest.clone(), digest.to_ascii_lowercase()] {
+            let bytes = read_cached(&digest, temp_dir.path()).await?;
+            assert_eq!(bytes.as_deref(), Some(&b"parcel"[..]));
+        }
+        assert_eq!(
+            entries(temp_dir.path()).await?[0].digest,
+            digest.to_ascii_lowercase()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_parcel_is_ignored() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let digest = sha256_digest(b"parcel");
+        std::fs::write(parcel_path(temp_dir.path(), &digest), b"tampered")?;
+
+        assert!(read_cached(&digest, temp_dir.path()).await?.is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+//! Inspection of the imports and exports of WebAssembly modules.
+
+#![deny(missing_docs)]
+
+use anyhow::{Context, Result};
+use spin_config::ModuleSource;
+use std::fmt::{Display, Formatter};
+
+/// Import modules the Spin execution context links into every component.
+const SATISFIED_IMPORT_MODULES: &[&str] = &[
+    // WASI, both the current and legacy snapshots.
+    "wasi_snapshot_preview1",
+    "wasi_unstable",
+    // Outbound HTTP, both the experimental and the WIT interfaces.
+    "wasi_experimental_http",
+    "wasi-outbound-http",
+    // Outbound Redis and the key-value stores.
+    "outbound-redis",
+    "spin-kv",
+];
+
+/// The export a module implementing the Spin HTTP interface must provide.
+pub const SPIN_HTTP_EXPORT: &str = "handle-http-request";
+/// The export a module implementing the Spin Redis interface must provide.
+pub const SPIN_REDIS_EXPORT: &str = "handle-redis-message";
+/// The default export a Wagi module must provide.
+pub const WAGI_DEFAULT_EXPORT: &str = "_start";
+
+/// A function, memory, table or global imported by a module.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Import {
+    /// The module the item is imported from.
+    pub module: String,
+    /// The name of the imported item.
+    pub name: String,
+}
+
+impl Display for Import {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}::{}", self.module, self.name)
+    }
+}
+
+/// An interface through which Spin can invoke a module.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interface {
+    /// The Spin HTTP interface.
+    SpinHttp,
+    /// The Spin Redis interface.
+    SpinRedis,
+    /// The Wagi CGI interface, with the default entrypoint.
+    Wagi,
+}
+
+impl Display for Interface {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::SpinHttp => "spin-http",
+            Self::SpinRedis => "spin-redis",
+            Self::Wagi => "wagi",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The imports and exports of a WebAssembly module.
+#[derive(Clone, Debug, Default)]
+pub struct ModuleInfo {
+    /// Items imported by the module.
+    pub imports: Vec<Import>,
+    /// Names of the functions exported by the module.
+    pub exports: Vec<String>,
+}
+
+impl ModuleInfo {
+    /// Returns true if the module exports a function with the given name.
+    pub fn exports(&self, name: &str) -> bool {
+        self.exports.iter().any(|e| e == name)
+    }
+
+    /// Returns the interfaces the exports of the module satisfy.
+    pub fn interfaces(&self) -> Vec<Interface> {
+        [
+            (Interface::SpinHttp, SPIN_HTTP_EXPORT),
+            (Interface::SpinRedis, SPIN_REDIS_EXPORT),
+            (Interface::Wagi, WAGI_DEFAULT_EXPORT),
+        ]
+        .into_iter()
+        .filter(|(_, export)| self.exports(export))
+        .map(|(i, _)| i)
+        .collect()
+    }
+
+    /// Returns the imports of the module Spin cannot satisfy.
+    pub fn unsatisfied_imports(&self) -> Vec<&Import> {
+        self.imports
+            .iter()
+            .filter(|i| !SATISFIED_IMPORT_MODULES.contains(&i.module.as_str()))
+            .collect()
+    }
+}
+
+/// Reads the imports and exports of a module from its binary.
+pub fn inspect(bytes: &[u8]) -> Result<ModuleInfo> {
+    let mut info = ModuleInfo::default();
+    for payload in wasmparser::Parser::new(0).parse_all(bytes) {
+        match payload? {
+            wasmparser::Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import?;
+                    info.imports.push(Import {
+                        module: import.module.to_owned(),
+                        name: import.field.unwrap_or_default().to_owned(),
+                    });
+                }
+            }
+            wasmparser::Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    if matches!(export.kind, wasmparser::ExternalKind::Function) {
+                        info.exports.push(export.field.to_owned());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+/// Reads the imports and exports of the module from a component source.
+pub async fn inspect_source(source: &ModuleSource) -> Result<ModuleInfo> {
+    match source {
+        ModuleSource::FileReference(p) => {
+            let bytes = tokio::fs::read(p)
+                .await
+                .with_context(|| format!("Cannot read module from {}", p.display()))?;
+            inspect(&bytes).with_context(|| format!("Cannot parse module {}", p.display()))
+        }
+        ModuleSource::Buffer(bytes, info) => {
+            inspect(bytes).with_context(|| format!("Cannot parse module from {}", info))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect_module() -> Result<()> {
+        let bytes = std::fs::read("tests/valid-with-files/spin-fs.wasm")?;
+        let info = inspect(&bytes)?;
+
+        assert!(info.exports("handler"));
+        assert!(info.exports("canonical_abi_realloc"));
+        assert!(info.interfaces().is_empty());
+        assert!(info
+            .imports
+            .iter()
+            .all(|i| i.module == "wasi_snapshot_preview1"));
+        assert!(info.unsatisfied_imports().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsatisfied_imports() {
+        let import = |module: &str| Import {
+            module: module.to_string(),
+            name: "f".to_string(),
+        };
+        let info = ModuleInfo {
+            imports: vec![
+                import("outbound-redis"),
+                import("spin-kv"),
+                import("wasi-cache"),
+            ],
+            exports: vec![],
+        };
+        assert_eq!(info.unsatisfied_imports(), vec![&import("wasi-cache")]);
+    }
+}
@@ -0,0 +1,177 @@
+#![deny(missing_docs)]
+
+use crate::assets::app_dir;
+use anyhow::{anyhow, bail, Context, Result};
+use spin_config::{KeyValueBackend, KeyValueStoreConfig};
+use std::{
+    collections::HashSet,
+    path::{Component, Path},
+};
+
+/// Validates the key-value stores declared by an application and resolves
+/// the paths of local stores.
+///
+/// Relative paths are resolved against `base` if given, and otherwise against
+/// the directory holding the state of the application, which the paths of
+/// applications run from a registry cannot leave. Local stores without a path
+/// are kept under `~/.spin/<app>_<hash>/kv`.
+pub(crate) fn prepare_stores(
+    stores: Vec<KeyValueStoreConfig>,
+    base: Option<&Path>,
+    app_name: &str,
+) -> Result<Vec<KeyValueStoreConfig>> {
+    let mut names = HashSet::new();
+    stores
+        .into_iter()
+        .map(|store| {
+            if !names.insert(store.name.clone()) {
+                bail!(
+                    "Key-value store '{}' is declared more than once",
+                    store.name
+                );
+            }
+            let name = store.name.clone();
+            prepare_store(store, base, app_name)
+                .with_context(|| anyhow!("Invalid key-value store '{}'", name))
+        })
+        .collect()
+}
+
+fn prepare_store(
+    mut store: KeyValueStoreConfig,
+    base: Option<&Path>,
+    app_name: &str,
+) -> Result<KeyValueStoreConfig> {
+    if store.name.is_empty() {
+        bail!("Store names cannot be empty");
+    }
+    let (uses_path, uses_address) = match store.backend {
+        KeyValueBackend::Memory => (false, false),
+        KeyValueBackend::Sqlite | KeyValueBackend::File => (true, false),
+        KeyValueBackend::Redis => (false, true),
+    };
+    if store.path.is_some() && !uses_path {
+        bail!("The '{}' backend does not use a path", store.backend);
+    }
+    if store.address.is_some() && !uses_address {
+        bail!("The '{}' backend does not use an address", store.backend);
+    }
+    if uses_address && store.address.is_none() {
+        bail!("The '{}' backend requires an address", store.backend);
+    }
+
+    if uses_path {
+        let path = match (store.path.take(), base) {
+            (Some(path), Some(base)) => base.join(path),
+            (Some(path), None) => {
+                if path.is_absolute() || path.components().any(|c| c == Component::ParentDir) {
+                    bail!(
+                        "Path '{}' must be relative and stay within the application directory",
+                        path.display()
+                    );
+                }
+                app_dir(app_name)?.join(path)
+            }
+            (None, _) => {
+                let file_name = match store.backend {
+                    KeyValueBackend::Sqlite => format!("{}.db", store.name),
+                    _ => store.name.clone(),
+                };
+                app_dir(app_name)?.join("kv").join(sanitize(&file_name))
+            }
+        };
+        store.path = Some(path);
+    }
+    Ok(store)
+}
+
+/// Ensures a component only refers to key-value stores declared by the application.
+pub(crate) fn check_component_stores(
+    id: &str,
+    names: &[String],
+    stores: &[KeyValueStoreConfig],
+) -> Result<()> {
+    for name in names {
+        if !stores.iter().any(|s| &s.name == name) {
+            bail!(
+                "Component '{}' uses key-value store '{}', which is not declared by the application",
+                id,
+                name
+            );
+        }
+    }
+    Ok(())
+}
+
+fn sanitize(name: &str) -> String {
+    name.replace(
+        |c: char| !c.is_ascii_alphanumeric() && c != '-' && c != '.',
+        "_",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn store(name: &str, backend: KeyValueBackend) -> KeyValueStoreConfig {
+        KeyValueStoreConfig {
+            name: name.to_string(),
+            backend,
+            path: None,
+            address: None,
+        }
+    }
+
+    #[test]
+    fn test_prepare_stores() {
+        let base = PathBuf::from("/app");
+        let stores = vec![
+            store("cache", KeyValueBackend::Memory),
+            KeyValueStoreConfig {
+                path: Some(PathBuf::from("data/kv.db")),
+                ..store("db", KeyValueBackend::Sqlite)
+            },
+            store("default", KeyValueBackend::File),
+        ];
+
+        let stores = prepare_stores(stores, Some(&base), "my app").unwrap();
+        assert_eq!(stores[0].path, None);
+        assert_eq!(stores[1].path, Some(PathBuf::from("/app/data/kv.db")));
+        assert_eq!(
+            stores[2].path,
+            Some(app_dir("my app").unwrap().join("kv").join("default"))
+        );
+    }
+
+    #[test]
+    fn test_invalid_stores() {
+        let duplicate = vec![
+            store("data", KeyValueBackend::Memory),
+            store("data", KeyValueBackend::File),
+        ];
+        assert!(prepare_stores(duplicate, None, "app").is_err());
+
+        let redis_without_address = vec![store("data", KeyValueBackend::Redis)];
+        assert!(prepare_stores(redis_without_address, None, "app").is_err());
+
+        let memory_with_path = vec![KeyValueStoreConfig {
+            path: Some(PathBuf::from("kv")),
+            ..store("data", KeyValueBackend::Memory)
+        }];
+        assert!(prepare_stores(memory_with_path, None, "app").is_err());
+
+        for path in ["/etc/kv", "../kv", "data/../../kv"] {
+            let escaping_path = vec![KeyValueStoreConfig {
+                path: Some(PathBuf::from(path)),
+                ..store("data", KeyValueBackend::File)
+            }];
+            assert!(prepare_stores(escaping_path, None, "app").is_err());
+        }
+
+        let stores = vec![store("data", KeyValueBackend::Memory)];
+        assert!(check_component_stores("c", &["data".to_string()], &stores).is_ok());
+        assert!(check_component_stores("c", &["other".to_string()], &stores).is_err());
+    }
+}
@@ -11,11 +11,25 @@
 #![deny(missing_docs)]
 
 mod assets;
+mod key_value;
 pub mod bindle;
+pub mod cache;
+pub mod inspect;
 pub mod local;
+pub mod oci;
 
 /// Load a Spin application configuration from a spin.toml manifest file.
 pub use local::from_file;
 
+/// Load a Spin application configuration from a spin.toml manifest file, choosing
+/// how the files of components are mounted.
+pub use local::{from_file_with_strategy, FilesMountStrategy};
+
 /// Load a Spin application configuration from Bindle.
 pub use crate::bindle::from_bindle;
+
+/// Load a Spin application configuration from a standalone bindle directory.
+pub use crate::bindle::from_standalone_bindle;
+
+/// Load a Spin application configuration from an OCI registry.
+pub use crate::oci::from_oci;
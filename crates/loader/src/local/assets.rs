@@ -10,13 +10,39 @@ use walkdir::WalkDir;
 
 use super::config::{RawDirectoryPlacement, RawFileMount};
 
+/// The file listing patterns of files to exclude from the file mounts of all
+/// components, in the same directory as spin.toml.
+pub const SPIN_IGNORE_FILE: &str = ".spinignore";
+
+/// How the files of a component are made available to it at runtime.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FilesMountStrategy {
+    /// Copy the files into the working directory, isolating the running
+    /// component from changes to the source tree.
+    Copy,
+    /// Mount directory placements directly from the source tree, read-only,
+    /// so that edits are visible without restarting. Files matched by patterns,
+    /// and placements that cannot be mounted on their own, are hard linked into
+    /// a read-only directory, or copied where hard links are not possible.
+    Direct,
+}
+
+impl Default for FilesMountStrategy {
+    fn default() -> Self {
+        Self::Direct
+    }
+}
+
 /// Prepare all local assets given a component ID and its file patterns.
-/// This file will copy all assets into a temporary directory as read-only.
+/// There is one mount for each guest destination, unless the files of one
+/// mount would be hidden by another mount, in which case they are merged.
 pub(crate) async fn prepare_component(
     raw_mounts: &[RawFileMount],
+    exclude_files: &[String],
     src: impl AsRef<Path>,
     base_dst: impl AsRef<Path>,
     id: &str,
+    strategy: FilesMountStrategy,
 ) -> Result<Vec<DirectoryMount>> {
     log::info!(
         "Mounting files from '{}' to '{}'",
@@ -24,12 +50,175 @@ pub(crate) async fn prepare_component(
         base_dst.as_ref().display()
     );
 
-    let files = collect(raw_mounts, src)?;
-    let host = create_dir(&base_dst, id).await?;
-    let guest = "/".to_string();
-    copy_all(&files, &host).await?;
+    let read_only = strategy == FilesMountStrategy::Direct;
+    let mut mounts = vec![];
+    for group in mount_groups(raw_mounts, exclude_files, &src)? {
+        let guest = group.destination.to_string_lossy().to_string();
+        if let (FilesMountStrategy::Direct, Some(source)) = (strategy, &group.source) {
+            log::trace!("Mounting '{}' directly at '{}'", source.display(), guest);
+            mounts.push(DirectoryMount {
+                guest,
+                host: source.clone(),
+                read_only,
+            });
+            continue;
+        }
+
+        let dir_id = match group.destination == Path::new("/") {
+            true => id.to_owned(),
+            false => format!("{}{}", id, guest),
+        };
+        let host = create_dir(&base_dst, &dir_id).await?;
+        copy_all(&group.relative_files()?, &host, strategy).await?;
+        mounts.push(DirectoryMount {
+            guest,
+            host,
+            read_only,
+        });
+    }
+
+    Ok(mounts)
+}
+
+/// Files mounted at the same guest destination.
+#[derive(Debug)]
+struct MountGroup {
+    /// The absolute guest path of the mount.
+    destination: PathBuf,
+    /// The files in the mount, relative to the guest root.
+    files: Vec<FileMount>,
+    /// The source directory, if the group is a single directory placement
+    /// that can be mounted as a whole.
+    source: Option<PathBuf>,
+}
+
+impl MountGroup {
+    fn merge(&mut self, other: MountGroup) {
+        self.files.extend(other.files);
+        self.source = None;
+    }
+
+    /// Returns the files, relative to the destination of the group.
+    fn relative_files(&self) -> Result<Vec<FileMount>> {
+        let destination = guest_relative(&self.destination);
+        self.files
+            .iter()
+            .map(|f| {
+                let relative_dst = to_relative(&f.relative_dst, destination)?;
+                Ok(FileMount {
+                    src: f.src.clone(),
+                    relative_dst,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Groups the files of a component by guest destination.
+fn mount_groups(
+    raw_mounts: &[RawFileMount],
+    exclude_files: &[String],
+    src: impl AsRef<Path>,
+) -> Result<Vec<MountGroup>> {
+    let src = src.as_ref();
+    let (patterns, placements) = uncase(raw_mounts);
+    let exclusions = Exclusions::new(exclude_files, src)?;
+
+    let mut groups: Vec<MountGroup> = vec![];
+    if !patterns.is_empty() {
+        groups.push(MountGroup {
+            destination: PathBuf::from("/"),
+            files: exclusions.filter(collect_patterns(&patterns, src)?, src),
+            source: None,
+        });
+    }
+    for placement in placements {
+        let all = collect_placements(std::slice::from_ref(&placement), src)?;
+        let total = all.len();
+        let files = exclusions.filter(all, src);
+        // A directory with excluded files cannot be exposed as a whole.
+        let source = (files.len() == total).then(|| src.join(&placement.source));
+        let group = MountGroup {
+            destination: placement.destination,
+            files,
+            source,
+        };
+        match groups
+            .iter_mut()
+            .find(|g| g.destination == group.destination)
+        {
+            Some(existing) => existing.merge(group),
+            None => groups.push(group),
+        }
+    }
+
+    // A mount nested in another mount hides the files of the outer mount under
+    // its destination, so such mounts are merged into the outer mount.
+    while let Some((inner, outer)) = find_hiding_mount(&groups) {
+        let group = groups.remove(inner);
+        let outer = if outer > inner { outer - 1 } else { outer };
+        groups[outer].merge(group);
+    }
 
-    Ok(vec![DirectoryMount { guest, host }])
+    for group in &mut groups {
+        group.files = deduplicate(std::mem::take(&mut group.files))?;
+    }
+    groups.sort_by(|a, b| a.destination.cmp(&b.destination));
+    Ok(groups)
+}
+
+/// Finds a mount whose destination hides files of another mount, returning
+/// the indexes of the inner and outer mounts.
+fn find_hiding_mount(groups: &[MountGroup]) -> Option<(usize, usize)> {
+    groups.iter().enumerate().find_map(|(inner, inner_group)| {
+        groups
+            .iter()
+            .enumerate()
+            .find(|(outer, outer_group)| {
+                *outer != inner
+                    && inner_group
+                        .destination
+                        .starts_with(&outer_group.destination)
+                    && outer_group
+                        .files
+                        .iter()
+                        .any(|f| is_under_guest_path(&f.relative_dst, &inner_group.destination))
+            })
+            .map(|(outer, _)| (inner, outer))
+    })
+}
+
+/// Removes files matched more than once, and fails if different files are
+/// mounted at the same guest path.
+fn deduplicate(files: Vec<FileMount>) -> Result<Vec<FileMount>> {
+    let mut by_destination = std::collections::HashMap::new();
+    let mut unique = vec![];
+    for file in files {
+        match by_destination.get(&file.relative_dst) {
+            None => {
+                by_destination.insert(file.relative_dst.clone(), file.src.clone());
+                unique.push(file);
+            }
+            Some(src) if src == &file.src => {}
+            Some(src) => bail!(
+                "Files '{}' and '{}' are both mounted at '/{}'",
+                src.display(),
+                file.src.display(),
+                file.relative_dst
+            ),
+        }
+    }
+    Ok(unique)
+}
+
+/// Returns an absolute guest path relative to the guest root.
+fn guest_relative(guest_path: &Path) -> &Path {
+    guest_path.strip_prefix("/").unwrap_or(guest_path)
+}
+
+/// Checks whether a file, relative to the guest root, is under an absolute guest path.
+fn is_under_guest_path(relative_dst: &str, guest_path: impl AsRef<Path>) -> bool {
+    Path::new(relative_dst).starts_with(guest_relative(guest_path.as_ref()))
 }
 
 /// A file that a component requires to be present at runtime.
@@ -59,14 +248,96 @@ impl FileMount {
     }
 }
 
-/// Generate a vector of file mounts for a component given all its file patterns.
-pub fn collect(raw_mounts: &[RawFileMount], rel: impl AsRef<Path>) -> Result<Vec<FileMount>> {
+/// Generate a vector of file mounts for a component given all its file patterns,
+/// leaving out the files matched by the exclusion patterns or by the patterns
+/// in the `.spinignore` file of the application directory.
+pub fn collect(
+    raw_mounts: &[RawFileMount],
+    exclude_files: &[String],
+    rel: impl AsRef<Path>,
+) -> Result<Vec<FileMount>> {
     let (patterns, placements) = uncase(raw_mounts);
+    let exclusions = Exclusions::new(exclude_files, &rel)?;
 
     let pattern_files = collect_patterns(&patterns, &rel)?;
     let placement_files = collect_placements(&placements, &rel)?;
     let all_files = [pattern_files, placement_files].concat();
-    Ok(all_files)
+    deduplicate(exclusions.filter(all_files, &rel))
+}
+
+/// Patterns of files to leave out of the file mounts.
+///
+/// A pattern containing a `/` is matched against the path relative to the
+/// application directory, and excludes everything below a matching directory.
+/// A pattern without a `/`, such as `*.swp` or `.git`, is matched against
+/// every component of the path, so it excludes matching files and directories
+/// at any depth.
+struct Exclusions {
+    anchored: Vec<glob::Pattern>,
+    unanchored: Vec<glob::Pattern>,
+}
+
+impl Exclusions {
+    fn new(exclude_files: &[String], rel: impl AsRef<Path>) -> Result<Self> {
+        let ignore_file = rel.as_ref().join(SPIN_IGNORE_FILE);
+        let ignored = match ignore_file.is_file() {
+            true => std::fs::read_to_string(&ignore_file)
+                .with_context(|| anyhow!("Cannot read {}", ignore_file.display()))?
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(str::to_owned)
+                .collect(),
+            false => vec![],
+        };
+
+        let mut exclusions = Self {
+            anchored: vec![],
+            unanchored: vec![],
+        };
+        for pattern in exclude_files.iter().chain(&ignored) {
+            let trimmed = pattern.trim_end_matches('/');
+            let anchored = trimmed.contains('/');
+            let glob = glob::Pattern::new(trimmed.trim_start_matches('/'))
+                .with_context(|| anyhow!("Invalid exclude pattern '{}'", pattern))?;
+            match anchored {
+                true => exclusions.anchored.push(glob),
+                false => exclusions.unanchored.push(glob),
+            }
+        }
+        Ok(exclusions)
+    }
+
+    fn filter(&self, files: Vec<FileMount>, rel: impl AsRef<Path>) -> Vec<FileMount> {
+        files
+            .into_iter()
+            .filter(|f| !self.excludes(&f.src, &rel))
+            .collect()
+    }
+
+    fn excludes(&self, path: impl AsRef<Path>, rel: impl AsRef<Path>) -> bool {
+        let relative = match to_relative(&path, &rel) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+        let components = relative.split('/').collect::<Vec<_>>();
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+
+        let unanchored = self
+            .unanchored
+            .iter()
+            .any(|p| components.iter().any(|c| p.matches_with(c, options)));
+        let anchored = self.anchored.iter().any(|p| {
+            (1..=components.len()).any(|n| p.matches_with(&components[..n].join("/"), options))
+        });
+        if unanchored || anchored {
+            log::trace!("Excluding file '{}' from file mounts", relative);
+        }
+        unanchored || anchored
+    }
 }
 
 fn collect_placements(
@@ -176,35 +447,73 @@ fn collect_pattern(pattern: &str, rel: impl AsRef<Path>) -> Result<Vec<FileMount
     Ok(files)
 }
 
-/// Copy all files to the mount directory.
-async fn copy_all(files: &[FileMount], dir: impl AsRef<Path>) -> Result<()> {
-    let res = future::join_all(files.iter().map(|f| copy(f, &dir))).await;
-    match res
-        .into_iter()
-        .filter_map(|r| r.err())
-        .map(|e| log::error!("{:?}", e))
-        .count()
-    {
-        0 => Ok(()),
-        n => bail!("Error copying assets: {} file(s) not copied", n),
+/// Copy all files to the mount directory, or link them if the files are mounted directly.
+async fn copy_all(
+    files: &[FileMount],
+    dir: impl AsRef<Path>,
+    strategy: FilesMountStrategy,
+) -> Result<()> {
+    let res = future::join_all(files.iter().map(|f| copy(f, &dir, strategy))).await;
+    let errors = res.into_iter().filter_map(|r| r.err()).collect::<Vec<_>>();
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    for e in &errors {
+        log::error!("{:?}", e);
     }
+    bail!(
+        "Error copying assets: {} file(s) not copied:\n{}",
+        errors.len(),
+        errors
+            .iter()
+            .map(|e| format!("  {:#}", e))
+            .collect::<Vec<_>>()
+            .join("\n")
+    )
 }
 
-/// Copy a single file to the mount directory, setting it as read-only.
-async fn copy(file: &FileMount, dir: impl AsRef<Path>) -> Result<()> {
+/// Copy or link a single file to the mount directory.
+async fn copy(file: &FileMount, dir: impl AsRef<Path>, strategy: FilesMountStrategy) -> Result<()> {
     let from = &file.src;
     let to = dir.as_ref().join(&file.relative_dst);
 
     ensure_under(&dir.as_ref(), &to.as_path())?;
 
+    tokio::fs::create_dir_all(to.parent().expect("Cannot copy to file '/'")).await?;
+
+    // A working directory may be reused across runs. A stale file must be
+    // removed rather than overwritten, as it may be a link to the source file.
+    if tokio::fs::symlink_metadata(&to).await.is_ok() {
+        tokio::fs::remove_file(&to).await?;
+    }
+
+    // Symbolic links cannot be used here: preopened directories do not follow
+    // links pointing outside of them.
+    if strategy == FilesMountStrategy::Direct {
+        match tokio::fs::hard_link(&from, &to).await {
+            Ok(()) => {
+                log::trace!(
+                    "Linked asset file '{}' -> '{}'",
+                    from.display(),
+                    to.display()
+                );
+                return Ok(());
+            }
+            Err(e) => log::debug!(
+                "Cannot link asset file '{}', copying it instead: {}",
+                from.display(),
+                e
+            ),
+        }
+    }
+
     log::trace!(
         "Copying asset file '{}' -> '{}'",
         from.display(),
         to.display()
     );
 
-    tokio::fs::create_dir_all(to.parent().expect("Cannot copy to file '/'")).await?;
-
     let _ = tokio::fs::copy(&from, &to)
         .await
         .with_context(|| anyhow!("Error copying asset file  '{}'", from.display()))?;
@@ -236,7 +545,7 @@ fn as_placement(fm: &RawFileMount) -> Option<RawDirectoryPlacement> {
     }
 }
 
-fn is_absolute_guest_path(path: impl AsRef<Path>) -> bool {
+pub(crate) fn is_absolute_guest_path(path: impl AsRef<Path>) -> bool {
     // We can't use `is_absolute` to check that guest paths are absolute,
     // because that would use the logic of the host filesystem.  If the
     // host is Windows, that would mean a path like `/assets` would not
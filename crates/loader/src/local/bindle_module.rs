@@ -0,0 +1,188 @@
+#![deny(missing_docs)]
+
+use crate::{
+    bindle::{BindleAuth, BindleConnectionInfo, BindleReader, SignaturePolicy},
+    cache::{read_cached, write_cached},
+};
+use anyhow::{anyhow, Context, Result};
+use spin_config::ModuleSource;
+use std::path::Path;
+
+use super::config::FileComponentBindleSource;
+
+const BINDLE_URL_ENV: &str = "BINDLE_URL";
+
+/// Prepares the module source of a component whose module is a parcel in a bindle,
+/// checking the invoice of the bindle against the signature policy.
+pub(crate) async fn prepare(
+    source: &FileComponentBindleSource,
+    policy: &SignaturePolicy,
+    cache_dir: impl AsRef<Path>,
+) -> Result<ModuleSource> {
+    // A module referenced by digest that is already cached can be used without
+    // contacting the Bindle server at all, unless the invoice must be signed.
+    if !policy.requires_signature() {
+        if let Some(bytes) = read_cached(&source.parcel, &cache_dir).await? {
+            return Ok(module_source(source, &source.parcel, bytes));
+        }
+    }
+
+    let server = match &source.server {
+        Some(s) => s.clone(),
+        None => std::env::var(BINDLE_URL_ENV).map_err(|_| {
+            anyhow!(
+                "Module source '{}' requires a Bindle server URL: set `server` or the {} environment variable",
+                source.reference,
+                BINDLE_URL_ENV
+            )
+        })?,
+    };
+    // Credentials come from the environment or the credentials file.
+    let client = BindleConnectionInfo::new(server, false, BindleAuth::from_env())?.client()?;
+    let id = source.reference.parse()?;
+    let reader = BindleReader::remote(&client, &id);
+
+    fetch(source, &reader, policy, cache_dir).await
+}
+
+/// Fetches the module parcel using the given reader, after checking the invoice
+/// against the signature policy, and saves it in the cache.
+pub(crate) async fn fetch(
+    source: &FileComponentBindleSource,
+    reader: &BindleReader,
+    policy: &SignaturePolicy,
+    cache_dir: impl AsRef<Path>,
+) -> Result<ModuleSource> {
+    let invoice = reader
+        .get_invoice()
+        .await
+        .with_context(|| anyhow!("Failed to load invoice '{}'", source.reference))?;
+    policy.verify(&invoice).await?;
+
+    // The parcel can be referenced either by its digest or by its name.
+    let label = invoice
+        .parcel
+        .iter()
+        .flatten()
+        .map(|p| &p.label)
+        .find(|l| l.sha256 == source.parcel || l.name == source.parcel)
+        .ok_or_else(|| {
+            anyhow!(
+                "Bindle '{}' does not contain parcel '{}'",
+                source.reference,
+                source.parcel
+            )
+        })?;
+
+    if let Some(bytes) = read_cached(&label.sha256, &cache_dir).await? {
+        return Ok(module_source(source, &label.sha256, bytes));
+    }
+
+    let bytes = reader
+        .get_parcel(label)
+        .await
+        .with_context(|| anyhow!("Cannot get parcel from bindle '{}'", source.reference))?;
+
+    write_cached(&label.sha256, &bytes, &cache_dir).await?;
+
+    Ok(module_source(source, &label.sha256, bytes))
+}
+
+fn module_source(source: &FileComponentBindleSource, digest: &str, bytes: Vec<u8>) -> ModuleSource {
+    ModuleSource::Buffer(bytes, format!("parcel {}@{}", source.reference, digest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::sha256_digest;
+    use bindle::invoice::signature::KeyRing;
+
+    const TEST_BINDLE_DIR: &str = "tests/standalone-bindle";
+    const TEST_BINDLE_ID: &str = "spin-module-test/1.0.0";
+    const TEST_MODULE_SHA: &str =
+        "93a44bbb96c751218e4c00d479e4c14358122a389acca16205b1e4d0dc5f9476";
+
+    fn test_source(parcel: &str) -> FileComponentBindleSource {
+        FileComponentBindleSource {
+            reference: TEST_BINDLE_ID.to_owned(),
+            parcel: parcel.to_owned(),
+            server: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_module_from_standalone_bindle() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let bindle_dir = temp_dir.path().join("bindle");
+        let cache_dir = temp_dir.path().join("cache");
+        fs_extra::dir::copy(
+            TEST_BINDLE_DIR,
+            &bindle_dir,
+            &fs_extra::dir::CopyOptions {
+                content_only: true,
+                ..Default::default()
+            },
+        )?;
+
+        let reader = BindleReader::standalone(&bindle_dir, &TEST_BINDLE_ID.parse()?).await?;
+
+        // Reference the parcel by name the first time.
+        let policy = SignaturePolicy::default();
+        match fetch(&test_source("module.wasm"), &reader, &policy, &cache_dir).await? {
+            ModuleSource::Buffer(bytes, _) => assert_eq!(sha256_digest(&bytes), TEST_MODULE_SHA),
+            ModuleSource::FileReference(_) => panic!("expected buffer module source"),
+        }
+        assert!(cache_dir.join(format!("{}.dat", TEST_MODULE_SHA)).is_file());
+
+        // Once cached, the module is available by digest without the bindle.
+        std::fs::remove_dir_all(&bindle_dir)?;
+        match prepare(&test_source(TEST_MODULE_SHA), &policy, &cache_dir).await? {
+            ModuleSource::Buffer(bytes, _) => assert_eq!(sha256_digest(&bytes), TEST_MODULE_SHA),
+            ModuleSource::FileReference(_) => panic!("expected buffer module source"),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_parcel_is_an_error() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let reader = BindleReader::standalone(TEST_BINDLE_DIR, &TEST_BINDLE_ID.parse()?).await?;
+
+        let policy = SignaturePolicy::default();
+        let err = fetch(
+            &test_source("missing.wasm"),
+            &reader,
+            &policy,
+            temp_dir.path(),
+        )
+        .await
+        .expect_err("expected missing parcel to fail");
+        assert!(err.to_string().contains("does not contain parcel"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_bindle_is_rejected_when_signatures_are_required() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let reader = BindleReader::standalone(TEST_BINDLE_DIR, &TEST_BINDLE_ID.parse()?).await?;
+
+        let policy = SignaturePolicy::require(KeyRing::new(vec![]));
+        assert!(fetch(
+            &test_source("module.wasm"),
+            &reader,
+            &policy,
+            temp_dir.path()
+        )
+        .await
+        .is_err());
+        assert!(!temp_dir
+            .path()
+            .join(format!("{}.dat", TEST_MODULE_SHA))
+            .exists());
+
+        Ok(())
+    }
+}
@@ -4,12 +4,18 @@
 
 #![deny(missing_docs)]
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use spin_config::{ApplicationTrigger, TriggerConfig};
+use spin_config::{ApplicationTrigger, KeyValueStoreConfig, OutboundHttpConfig, TriggerConfig};
 use std::{collections::HashMap, path::PathBuf};
 
+/// Generates the JSON schema for spin.toml manifests of any version.
+pub fn manifest_schema() -> schemars::schema::RootSchema {
+    schemars::schema_for!(RawAppManifestAnyVersion)
+}
+
 /// Container for any version of the manifest.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(tag = "spin_version")]
 pub enum RawAppManifestAnyVersion {
     /// A manifest with API version 1.
@@ -19,20 +25,24 @@ pub enum RawAppManifestAnyVersion {
 
 /// Application configuration local file format.
 /// This is the main structure spin.toml deserializes into.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub struct RawAppManifest {
     /// General application information.
     #[serde(flatten)]
     pub info: RawAppInformation,
 
+    /// Key-value stores available to the application components.
+    #[serde(rename = "key_value_store")]
+    pub key_value_stores: Option<Vec<KeyValueStoreConfig>>,
+
     /// Configuration for the application components.
     #[serde(rename = "component")]
     pub components: Vec<RawComponentManifest>,
 }
 
 /// General application information.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub struct RawAppInformation {
     /// Name of the application.
@@ -50,7 +60,7 @@ pub struct RawAppInformation {
 }
 
 /// Core component configuration.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub struct RawComponentManifest {
     /// The module source.
@@ -63,10 +73,23 @@ pub struct RawComponentManifest {
     pub wasm: RawWasmConfig,
     /// Trigger configuration.
     pub trigger: TriggerConfig,
+    /// Build configuration for the component.
+    pub build: Option<RawBuildConfig>,
+}
+
+/// Build configuration for the component.
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct RawBuildConfig {
+    /// Build command.
+    pub command: String,
+    /// Working directory in which the build command is executed. It must be
+    /// relative to the directory in which `spin.toml` is located.
+    pub workdir: Option<PathBuf>,
 }
 
 /// WebAssembly configuration.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub struct RawWasmConfig {
     /// Environment variables to be mapped inside the Wasm module at runtime.
@@ -77,13 +100,26 @@ pub struct RawWasmConfig {
     /// is either a file path or glob relative to the spin.toml file, or a
     /// mapping of a source path to an absolute mount path in the guest.
     pub files: Option<Vec<RawFileMount>>,
+    /// Glob patterns of files to leave out of `files`, relative to the spin.toml
+    /// file. Patterns without a `/` exclude matching files and directories at
+    /// any depth, for example `*.swp` or `.git`.
+    pub exclude_files: Option<Vec<String>>,
+    /// Host directories to be mounted inside the Wasm module at runtime, which
+    /// the module may be allowed to modify.
+    pub volumes: Option<Vec<RawVolume>>,
     /// Optional list of HTTP hosts the component is allowed to connect.
     pub allowed_http_hosts: Option<Vec<String>>,
+    /// Optional list of Redis server addresses the component is allowed to connect.
+    pub allowed_redis_addresses: Option<Vec<String>>,
+    /// Names of the application key-value stores the component is allowed to use.
+    pub key_value_stores: Option<Vec<String>>,
+    /// Limits of the outbound HTTP requests of the component.
+    pub outbound_http: Option<OutboundHttpConfig>,
 }
 
 /// An entry in the `files` list mapping a source path to an absolute
 /// mount path in the guest.
-#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub struct RawDirectoryPlacement {
     /// The source to mount.
@@ -92,9 +128,40 @@ pub struct RawDirectoryPlacement {
     pub destination: PathBuf,
 }
 
+/// A host directory mounted in the Wasm module, for example to persist data
+/// between requests.
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct RawVolume {
+    /// The host directory, relative to the spin.toml file. If omitted, a
+    /// directory under the data directory of the application in `~/.spin` is used.
+    pub source: Option<PathBuf>,
+    /// The absolute mount path inside the Wasm module.
+    pub destination: PathBuf,
+    /// Whether the module may modify the directory. Defaults to read-only.
+    #[serde(default)]
+    pub mode: RawVolumeMode,
+}
+
+/// The access mode of a volume.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RawVolumeMode {
+    /// The module can only read the directory.
+    ReadOnly,
+    /// The module can create, modify and remove files in the directory.
+    ReadWrite,
+}
+
+impl Default for RawVolumeMode {
+    fn default() -> Self {
+        Self::ReadOnly
+    }
+}
+
 /// A specification for a file or set of files to mount in the
 /// Wasm module.
-#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "snake_case", untagged)]
 pub enum RawFileMount {
     /// Mount a specified directory at a specified location.
@@ -104,24 +171,40 @@ pub enum RawFileMount {
 }
 
 /// Source for the module.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "snake_case", untagged)]
 pub enum RawModuleSource {
     /// Local path or parcel reference to a module that needs to be linked.
     FileReference(PathBuf),
     /// Reference to a remote bindle
     Bindle(FileComponentBindleSource),
+    /// URL of a remote module, pinned by its digest
+    Url(FileComponentUrlSource),
 }
 
 /// A component source from Bindle.
-/// TODO
-/// The component and its entrypoint should be pulled from Bindle.
-/// This assumes access to the Bindle server.
-#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+/// The module is pulled from the Bindle server and cached locally by its digest,
+/// so that subsequent loads do not require access to the server.
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub struct FileComponentBindleSource {
     /// Reference to the bindle (name/version)
     pub reference: String,
-    /// Parcel to use from the bindle.
+    /// Parcel to use from the bindle, by SHA256 digest or by name.
     pub parcel: String,
+    /// URL of the Bindle server. If omitted, the `BINDLE_URL` environment
+    /// variable is used.
+    pub server: Option<String>,
+}
+
+/// A component source downloaded from a URL.
+/// The module is downloaded once, verified against the digest, and cached locally
+/// so that subsequent loads do not require network access.
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct FileComponentUrlSource {
+    /// The URL of the module.
+    pub url: String,
+    /// The digest of the module, in the form `sha256:<hex digest>`.
+    pub digest: String,
 }
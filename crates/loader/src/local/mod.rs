@@ -4,19 +4,29 @@
 
 /// Module to prepare the assets for the components of an application.
 pub mod assets;
+/// Module to fetch component modules referenced from Bindle.
+mod bindle_module;
 /// Configuration representation for a Spin application as a local spin.toml file.
 pub mod config;
+/// Module to download component modules referenced by URL.
+mod url_module;
+/// Validation of a local spin.toml file.
+pub mod validation;
+/// Module to prepare the volumes of components.
+mod volumes;
 
 #[cfg(test)]
 mod tests;
 
+use crate::{bindle::SignaturePolicy, key_value};
 use anyhow::{anyhow, Context, Result};
+pub use assets::FilesMountStrategy;
 use config::{RawAppInformation, RawAppManifest, RawAppManifestAnyVersion, RawComponentManifest};
 use futures::future;
 use path_absolutize::Absolutize;
 use spin_config::{
-    Application, ApplicationInformation, ApplicationOrigin, CoreComponent, ModuleSource,
-    SpinVersion, WasmConfig,
+    parse_allowed_network, AllowedHttpHost, Application, ApplicationInformation, ApplicationOrigin,
+    CoreComponent, ModuleSource, SpinVersion, WasmConfig,
 };
 use std::path::Path;
 use tokio::{fs::File, io::AsyncReadExt};
@@ -25,9 +35,25 @@ use tokio::{fs::File, io::AsyncReadExt};
 /// get a prepared application configuration consumable by a Spin execution context.
 /// If a directory is provided, use it as the base directory to expand the assets,
 /// otherwise create a new temporary directory.
+/// The files of components are mounted directly from the application directory,
+/// and the bindles of components are checked against the default signature policy.
 pub async fn from_file(
     app: impl AsRef<Path>,
     base_dst: impl AsRef<Path>,
+) -> Result<Application<CoreComponent>> {
+    let policy = SignaturePolicy::new(false, None).await?;
+    from_file_with_strategy(app, base_dst, FilesMountStrategy::default(), &policy).await
+}
+
+/// Given the path to a spin.toml manifest file, prepare its assets locally using
+/// the given strategy, and get a prepared application configuration consumable
+/// by a Spin execution context. The invoices of the bindles that component
+/// modules are fetched from are checked against the signature policy.
+pub async fn from_file_with_strategy(
+    app: impl AsRef<Path>,
+    base_dst: impl AsRef<Path>,
+    strategy: FilesMountStrategy,
+    policy: &SignaturePolicy,
 ) -> Result<Application<CoreComponent>> {
     let app = app
         .as_ref()
@@ -35,7 +61,7 @@ pub async fn from_file(
         .context("Failed to resolve absolute path to manifest file")?;
     let manifest = raw_manifest_from_file(&app).await?;
 
-    prepare_any_version(manifest, app, base_dst).await
+    prepare_any_version(manifest, app, base_dst, strategy, policy).await
 }
 
 /// Reads the spin.toml file as a raw manifest.
@@ -57,9 +83,11 @@ async fn prepare_any_version(
     raw: RawAppManifestAnyVersion,
     src: impl AsRef<Path>,
     base_dst: impl AsRef<Path>,
+    strategy: FilesMountStrategy,
+    policy: &SignaturePolicy,
 ) -> Result<Application<CoreComponent>> {
     match raw {
-        RawAppManifestAnyVersion::V1(raw) => prepare(raw, src, base_dst).await,
+        RawAppManifestAnyVersion::V1(raw) => prepare(raw, src, base_dst, strategy, policy).await,
     }
 }
 
@@ -68,37 +96,62 @@ async fn prepare(
     raw: RawAppManifest,
     src: impl AsRef<Path>,
     base_dst: impl AsRef<Path>,
+    strategy: FilesMountStrategy,
+    policy: &SignaturePolicy,
 ) -> Result<Application<CoreComponent>> {
     let info = info(raw.info, &src);
+    let app_dir = src
+        .as_ref()
+        .parent()
+        .expect("The application file did not have a parent directory.");
+    let key_value_stores = key_value::prepare_stores(
+        raw.key_value_stores.unwrap_or_default(),
+        Some(app_dir),
+        &info.name,
+    )?;
 
     let component_triggers = raw
         .components
         .iter()
         .map(|c| (c.id.clone(), c.trigger.clone()))
         .collect();
+    let uses_cache = raw
+        .components
+        .iter()
+        .any(|c| !matches!(c.source, config::RawModuleSource::FileReference(_)));
     let components = future::join_all(
         raw.components
             .into_iter()
-            .map(|c| async { core(c, &src, &base_dst).await })
+            .map(|c| async { core(c, &info.name, &src, &base_dst, strategy, policy).await })
             .collect::<Vec<_>>(),
     )
     .await
     .into_iter()
     .collect::<Result<Vec<_>>>()
     .context("Failed to prepare configuration")?;
+    if uses_cache {
+        crate::cache::prune_after_load(crate::cache::parcel_cache_dir(&base_dst)).await;
+    }
+    for c in &components {
+        key_value::check_component_stores(&c.id, &c.wasm.key_value_stores, &key_value_stores)?;
+    }
 
     Ok(Application {
         info,
         components,
         component_triggers,
+        key_value_stores,
     })
 }
 
 /// Given a raw component manifest, prepare its assets and return a fully formed core component.
 async fn core(
     raw: RawComponentManifest,
+    app_name: &str,
     src: impl AsRef<Path>,
     base_dst: impl AsRef<Path>,
+    strategy: FilesMountStrategy,
+    policy: &SignaturePolicy,
 ) -> Result<CoreComponent> {
     let src = src
         .as_ref()
@@ -113,22 +166,52 @@ async fn core(
 
             ModuleSource::FileReference(p)
         }
-        config::RawModuleSource::Bindle(_) => {
-            todo!("Bindle module sources are not yet supported in file-based app config")
+        config::RawModuleSource::Bindle(b) => {
+            let cache_dir = crate::cache::parcel_cache_dir(&base_dst);
+            bindle_module::prepare(&b, policy, cache_dir)
+                .await
+                .with_context(|| format!("Failed to prepare module for component {}", raw.id))?
+        }
+        config::RawModuleSource::Url(u) => {
+            let cache_dir = crate::cache::parcel_cache_dir(&base_dst);
+            url_module::prepare(&u, cache_dir)
+                .await
+                .with_context(|| format!("Failed to prepare module for component {}", raw.id))?
         }
     };
 
     let id = raw.id;
-    let mounts = match raw.wasm.files {
-        Some(f) => assets::prepare_component(&f, src, &base_dst, &id).await?,
+    let mut mounts = match raw.wasm.files {
+        Some(f) => {
+            let exclude_files = raw.wasm.exclude_files.unwrap_or_default();
+            assets::prepare_component(&f, &exclude_files, src, &base_dst, &id, strategy).await?
+        }
         None => vec![],
     };
+    if let Some(v) = raw.wasm.volumes {
+        let volumes = volumes::prepare_component(&v, src, app_name, &id, &mounts)
+            .await
+            .with_context(|| format!("Failed to prepare volumes for component {}", id))?;
+        mounts.extend(volumes);
+    }
     let environment = raw.wasm.environment.unwrap_or_default();
     let allowed_http_hosts = raw.wasm.allowed_http_hosts.unwrap_or_default();
+    AllowedHttpHost::parse_all(&allowed_http_hosts)
+        .with_context(|| format!("Invalid allowed HTTP hosts for component {}", id))?;
+    let allowed_redis_addresses = raw.wasm.allowed_redis_addresses.unwrap_or_default();
+    let key_value_stores = raw.wasm.key_value_stores.unwrap_or_default();
+    let outbound_http = raw.wasm.outbound_http.unwrap_or_default();
+    for network in &outbound_http.allowed_networks {
+        parse_allowed_network(network)
+            .with_context(|| format!("Invalid allowed network for component {}", id))?;
+    }
     let wasm = WasmConfig {
         environment,
         mounts,
         allowed_http_hosts,
+        allowed_redis_addresses,
+        key_value_stores,
+        outbound_http,
     };
     Ok(CoreComponent { source, id, wasm })
 }
@@ -1,4 +1,4 @@
-use crate::local::config::{RawDirectoryPlacement, RawFileMount, RawModuleSource};
+use crate::local::config::{RawBuildConfig, RawDirectoryPlacement, RawFileMount, RawModuleSource};
 
 use super::*;
 use anyhow::Result;
@@ -89,9 +89,18 @@ fn test_manifest() -> Result<()> {
         RawFileMount::Pattern("subdir/another.txt".to_owned())
     );
 
+    assert_eq!(
+        test_component.build,
+        Some(RawBuildConfig {
+            command: "cargo build --target wasm32-wasi --release".to_owned(),
+            workdir: Some(PathBuf::from("four-lights")),
+        })
+    );
+    assert_eq!(cfg.components[1].build, None);
+
     let b = match cfg.components[1].source.clone() {
         RawModuleSource::Bindle(b) => b,
-        RawModuleSource::FileReference(_) => panic!("expected bindle source"),
+        _ => panic!("expected bindle source"),
     };
 
     assert_eq!(b.reference, "bindle reference".to_string());
@@ -139,3 +148,345 @@ fn test_wagi_executor_with_custom_entrypoint() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_validate_reports_all_problems() -> Result<()> {
+    use validation::Severity;
+
+    const MANIFEST: &str = "tests/invalid-manifest.toml";
+
+    let diagnostics = validation::validate(MANIFEST).await?;
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .collect::<Vec<_>>();
+    let warnings = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Warning)
+        .collect::<Vec<_>>();
+
+    let has_error = |line: usize, text: &str| {
+        errors
+            .iter()
+            .any(|d| d.line == Some(line) && d.message.contains(text))
+    };
+    assert!(has_error(6, "does not export 'handle-http-request'"));
+    assert!(has_error(10, "'not a url' is not a valid URL"));
+    assert!(has_error(10, "uses a wildcard with an IP address"));
+    assert!(has_error(11, "'http://localhost' is not a valid Redis URL"));
+    assert!(has_error(12, "'10.0.0.0/33' is not a valid IP address or network"));
+    assert!(has_error(16, "Duplicate component ID 'duplicate'"));
+    assert!(has_error(16, "must have an HTTP trigger"));
+    assert!(has_error(16, "cannot be read"));
+    assert_eq!(errors.len(), 8);
+
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].message.contains("does not match any files"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_validate_reports_syntax_error_line() -> Result<()> {
+    const MANIFEST: &str = "tests/syntax-error.toml";
+
+    let diagnostics = validation::validate(MANIFEST).await?;
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].line, Some(7));
+    assert!(diagnostics[0]
+        .to_string()
+        .starts_with(&format!("{}:7: error", MANIFEST)));
+
+    Ok(())
+}
+
+#[test]
+fn test_schema_matches_manifests() -> Result<()> {
+    let schema = serde_json::to_value(config::manifest_schema())?;
+    let schema = jsonschema::JSONSchema::compile(&schema).expect("Manifest schema should compile");
+
+    let to_json = |manifest: &str| -> Result<serde_json::Value> {
+        let value: toml::Value = toml::from_str(manifest)?;
+        Ok(serde_json::to_value(value)?)
+    };
+
+    for manifest in [
+        include_str!("../../tests/valid-manifest.toml"),
+        include_str!("../../tests/wagi-custom-entrypoint.toml"),
+        include_str!("../../tests/valid-with-files/spin.toml"),
+    ] {
+        // Anything serde accepts, the schema must accept too.
+        toml::from_str::<RawAppManifestAnyVersion>(manifest)?;
+        assert!(schema.is_valid(&to_json(manifest)?));
+    }
+
+    let invalid = include_str!("../../tests/invalid-version.toml");
+    assert!(!schema.is_valid(&to_json(invalid)?));
+
+    Ok(())
+}
+
+#[test]
+fn test_exclude_files() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let app_dir = temp_dir.path();
+    for file in [
+        "content/index.html",
+        "content/.index.html.swp",
+        "content/.git/config",
+        "content/drafts/post.md",
+        "static/drafts/image.png",
+    ] {
+        let path = app_dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, file)?;
+    }
+    std::fs::write(
+        app_dir.join(assets::SPIN_IGNORE_FILE),
+        "# Editor swap files\n*.swp\n\n.git/\n",
+    )?;
+
+    let mounts = vec![
+        RawFileMount::Pattern("content/**/*".to_owned()),
+        RawFileMount::Placement(RawDirectoryPlacement {
+            source: PathBuf::from("static"),
+            destination: PathBuf::from("/static"),
+        }),
+    ];
+    let exclude_files = vec!["content/drafts".to_owned()];
+    let mut files = assets::collect(&mounts, &exclude_files, app_dir)?
+        .into_iter()
+        .map(|f| f.relative_dst)
+        .collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(files, vec!["content/index.html", "static/drafts/image.png"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_direct_mounts() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let app_dir = temp_dir.path().join("app");
+    let base_dst = temp_dir.path().join("working");
+    for file in ["static/logo.png", "content/index.html", "root/about.html"] {
+        let path = app_dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, file)?;
+    }
+    let placement = |source: &str, destination: &str| {
+        RawFileMount::Placement(RawDirectoryPlacement {
+            source: PathBuf::from(source),
+            destination: PathBuf::from(destination),
+        })
+    };
+    let strategy = assets::FilesMountStrategy::Direct;
+
+    // A placement that shares no guest paths is mounted from the source tree.
+    let mounts = vec![
+        placement("static", "/static"),
+        RawFileMount::Pattern("content/*".to_owned()),
+    ];
+    let mounts =
+        assets::prepare_component(&mounts, &[], &app_dir, &base_dst, "direct", strategy).await?;
+    assert_eq!(mounts.len(), 2);
+    assert!(mounts.iter().all(|m| m.read_only));
+    let direct = mounts.iter().find(|m| m.guest == "/static").unwrap();
+    assert_eq!(direct.host, app_dir.join("static"));
+    let linked = mounts.iter().find(|m| m.guest == "/").unwrap();
+    assert_eq!(
+        std::fs::read_to_string(linked.host.join("content/index.html"))?,
+        "content/index.html"
+    );
+
+    // A placement at the root would hide the linked files, so it is linked too.
+    let mounts = vec![
+        placement("root", "/"),
+        RawFileMount::Pattern("content/*".to_owned()),
+    ];
+    let mounts =
+        assets::prepare_component(&mounts, &[], &app_dir, &base_dst, "merged", strategy).await?;
+    assert_eq!(mounts.len(), 1);
+    assert!(mounts[0].host.join("about.html").is_file());
+    assert!(mounts[0].host.join("content/index.html").is_file());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mount_per_destination() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let app_dir = temp_dir.path().join("app");
+    let base_dst = temp_dir.path().join("working");
+    for file in [
+        "static/logo.png",
+        "config/app.toml",
+        "content/index.html",
+        "drafts/index.html",
+    ] {
+        let path = app_dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, file)?;
+    }
+    let placement = |source: &str, destination: &str| {
+        RawFileMount::Placement(RawDirectoryPlacement {
+            source: PathBuf::from(source),
+            destination: PathBuf::from(destination),
+        })
+    };
+    let strategy = assets::FilesMountStrategy::Copy;
+
+    let mounts = vec![
+        placement("static", "/static"),
+        placement("config", "/config"),
+        RawFileMount::Pattern("content/*".to_owned()),
+    ];
+    let mounts =
+        assets::prepare_component(&mounts, &[], &app_dir, &base_dst, "split", strategy).await?;
+    let guests = mounts.iter().map(|m| m.guest.as_str()).collect::<Vec<_>>();
+    assert_eq!(guests, vec!["/", "/config", "/static"]);
+    assert!(mounts[1].host.join("app.toml").is_file());
+    assert!(mounts[2].host.join("logo.png").is_file());
+    assert!(mounts.iter().all(|m| !m.read_only));
+
+    // Two sources mapping to the same guest file are reported.
+    let mounts = vec![
+        placement("drafts", "/content"),
+        RawFileMount::Pattern("content/*".to_owned()),
+    ];
+    let err = assets::prepare_component(&mounts, &[], &app_dir, &base_dst, "clash", strategy)
+        .await
+        .expect_err("expected colliding files to fail");
+    assert!(err
+        .to_string()
+        .contains("are both mounted at '/content/index.html'"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_volumes() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let app_dir = temp_dir.path();
+    std::fs::create_dir_all(app_dir.join("static/data"))?;
+    let file_mounts = vec![spin_config::DirectoryMount {
+        guest: "/".to_owned(),
+        host: app_dir.join("static"),
+        read_only: true,
+    }];
+
+    let manifest: config::RawWasmConfig = toml::from_str(
+        r#"
+        volumes = [
+            { source = "state", destination = "/state", mode = "read-write" },
+            { source = "static", destination = "/assets" },
+        ]
+        "#,
+    )?;
+    let volumes = manifest.volumes.unwrap();
+    let mounts = volumes::prepare_component(&volumes, app_dir, "app", "c", &[]).await?;
+    assert_eq!(mounts.len(), 2);
+    assert!(app_dir.join("state").is_dir());
+    assert!(!mounts[0].read_only);
+    assert!(mounts[1].read_only);
+
+    let volume = |source: &str, destination: &str, mode: config::RawVolumeMode| config::RawVolume {
+        source: Some(PathBuf::from(source)),
+        destination: PathBuf::from(destination),
+        mode,
+    };
+    let read_only = config::RawVolumeMode::ReadOnly;
+    let read_write = config::RawVolumeMode::ReadWrite;
+    for invalid in [
+        // Read-only volumes are not created.
+        vec![volume("missing", "/missing", read_only)],
+        // Volumes must stay under the application directory.
+        vec![volume("../outside", "/outside", read_write)],
+        // Volumes cannot be nested.
+        vec![
+            volume("state", "/state", read_write),
+            volume("static", "/state/static", read_only),
+        ],
+        // Volumes cannot hide the files of other mounts.
+        vec![volume("state", "/data", read_write)],
+        // Guest paths cannot climb out of the application data directory.
+        vec![config::RawVolume {
+            source: None,
+            destination: PathBuf::from("/../../../../tmp"),
+            mode: read_write,
+        }],
+        vec![volume("state", "/./state", read_write)],
+    ] {
+        assert!(
+            volumes::prepare_component(&invalid, app_dir, "app", "c", &file_mounts)
+                .await
+                .is_err(),
+            "expected {:?} to fail",
+            invalid
+        );
+    }
+
+    // Volumes cannot contain the files of other mounts either.
+    let nested_files = vec![spin_config::DirectoryMount {
+        guest: "/state/static".to_owned(),
+        host: app_dir.join("static"),
+        read_only: true,
+    }];
+    let err = volumes::prepare_component(
+        &[volume("state", "/state", read_write)],
+        app_dir,
+        "app",
+        "c",
+        &nested_files,
+    )
+    .await
+    .expect_err("expected files inside a volume to fail");
+    assert!(err.to_string().contains("would be inside volume"), "{:#}", err);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_key_value_stores() -> Result<()> {
+    const MANIFEST: &str = r#"
+        spin_version = "1"
+        name = "kv-test"
+        trigger = { type = "http", base = "/" }
+        version = "1.0.0"
+
+        [[key_value_store]]
+        name = "default"
+        backend = "sqlite"
+        path = "data/kv.db"
+
+        [[key_value_store]]
+        name = "cache"
+        backend = "memory"
+
+        [[component]]
+        id = "counter"
+        source = "counter.wasm"
+        key_value_stores = ["default"]
+        [component.trigger]
+        route = "/counter"
+    "#;
+
+    let temp_dir = tempfile::tempdir()?;
+    let app_dir = temp_dir.path();
+    let app_file = app_dir.join("spin.toml");
+    std::fs::write(&app_file, MANIFEST)?;
+
+    let app = from_file(&app_file, app_dir.join("work")).await?;
+    assert_eq!(app.key_value_stores.len(), 2);
+    assert_eq!(
+        app.key_value_stores[0].path,
+        Some(app_dir.join("data/kv.db"))
+    );
+    assert_eq!(app.components[0].wasm.key_value_stores, vec!["default"]);
+
+    let undeclared = MANIFEST.replace(r#"["default"]"#, r#"["other"]"#);
+    std::fs::write(&app_file, undeclared)?;
+    assert!(from_file(&app_file, app_dir.join("work")).await.is_err());
+
+    Ok(())
+}
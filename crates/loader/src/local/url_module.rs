@@ -0,0 +1,174 @@
+#![deny(missing_docs)]
+
+use anyhow::{anyhow, bail, Context, Result};
+use spin_config::ModuleSource;
+use std::path::Path;
+use tracing::log;
+
+use super::config::FileComponentUrlSource;
+use crate::cache::{read_cached, sha256_digest, write_cached};
+
+const SHA256_DIGEST_PREFIX: &str = "sha256:";
+
+/// Prepares the module source of a component whose module is downloaded from a URL.
+/// The module is only downloaded if it is not already in the cache, and it is
+/// always verified against the digest in the manifest before being used.
+pub(crate) async fn prepare(
+    source: &FileComponentUrlSource,
+    cache_dir: impl AsRef<Path>,
+) -> Result<ModuleSource> {
+    let digest = parse_digest(&source.digest)?;
+
+    if let Some(bytes) = read_cached(&digest, &cache_dir).await? {
+        return Ok(module_source(source, bytes));
+    }
+
+    log::info!("Downloading module from {}", source.url);
+    let bytes = reqwest::get(&source.url)
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| anyhow!("Failed to download module from {}", source.url))?
+        .bytes()
+        .await
+        .with_context(|| anyhow!("Failed to download module from {}", source.url))?
+        .to_vec();
+
+    let actual = sha256_digest(&bytes);
+    if actual != digest {
+        bail!(
+            "Module downloaded from {} has digest {}{}, expected {}",
+            source.url,
+            SHA256_DIGEST_PREFIX,
+            actual,
+            source.digest
+        );
+    }
+
+    write_cached(&digest, &bytes, &cache_dir).await?;
+
+    Ok(module_source(source, bytes))
+}
+
+/// Returns the hex-encoded value of a `sha256:<hex>` digest, in lowercase.
+fn parse_digest(digest: &str) -> Result<String> {
+    match digest.strip_prefix(SHA256_DIGEST_PREFIX) {
+        Some(hex) if hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+            Ok(hex.to_ascii_lowercase())
+        }
+        _ => bail!(
+            "Invalid module digest '{}': expected '{}' followed by 64 hex digits",
+            digest,
+            SHA256_DIGEST_PREFIX
+        ),
+    }
+}
+
+fn module_source(source: &FileComponentUrlSource, bytes: Vec<u8>) -> ModuleSource {
+    ModuleSource::Buffer(bytes, format!("{} ({})", source.url, source.digest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Body, Response, Server,
+    };
+    use std::{
+        convert::Infallible,
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    const TEST_MODULE: &[u8] = b"\0asm\x01\0\0\0";
+    const TEST_MODULE_DIGEST: &str =
+        "sha256:93a44bbb96c751218e4c00d479e4c14358122a389acca16205b1e4d0dc5f9476";
+
+    /// Serves the test module on a local port, counting the requests made.
+    fn serve_module() -> (SocketAddr, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let make_svc = make_service_fn(move |_| {
+            let counter = counter.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |_req| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    async { Ok::<_, Infallible>(Response::new(Body::from(TEST_MODULE))) }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_svc);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        (addr, requests)
+    }
+
+    fn test_source(addr: SocketAddr, digest: &str) -> FileComponentUrlSource {
+        FileComponentUrlSource {
+            url: format!("http://{}/module.wasm", addr),
+            digest: digest.to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_module_is_downloaded_once() -> Result<()> {
+        let (addr, requests) = serve_module();
+        let cache_dir = tempfile::tempdir()?;
+        let source = test_source(addr, TEST_MODULE_DIGEST);
+
+        for _ in 0..2 {
+            match prepare(&source, cache_dir.path()).await? {
+                ModuleSource::Buffer(bytes, _) => assert_eq!(bytes, TEST_MODULE),
+                ModuleSource::FileReference(_) => panic!("expected buffer module source"),
+            }
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_uppercase_digest_is_accepted() -> Result<()> {
+        let (addr, _) = serve_module();
+        let cache_dir = tempfile::tempdir()?;
+        let digest = format!(
+            "{}{}",
+            SHA256_DIGEST_PREFIX,
+            TEST_MODULE_DIGEST[SHA256_DIGEST_PREFIX.len()..].to_ascii_uppercase()
+        );
+        let source = test_source(addr, &digest);
+
+        match prepare(&source, cache_dir.path()).await? {
+            ModuleSource::Buffer(bytes, _) => assert_eq!(bytes, TEST_MODULE),
+            ModuleSource::FileReference(_) => panic!("expected buffer module source"),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_digest_mismatch_is_rejected() -> Result<()> {
+        let (addr, _) = serve_module();
+        let cache_dir = tempfile::tempdir()?;
+        let wrong_digest = format!("sha256:{}", "0".repeat(64));
+        let source = test_source(addr, &wrong_digest);
+
+        let err = prepare(&source, cache_dir.path())
+            .await
+            .expect_err("expected digest mismatch to fail");
+        assert!(err.to_string().contains("expected"));
+        assert!(std::fs::read_dir(cache_dir.path())?.next().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_digest() {
+        assert!(parse_digest(TEST_MODULE_DIGEST).is_ok());
+        assert!(parse_digest("sha256:abc").is_err());
+        assert!(parse_digest("md5:93a44bbb96c751218e4c00d479e4c143").is_err());
+    }
+}
@@ -0,0 +1,313 @@
+//! Validation of spin.toml application manifests.
+
+#![deny(missing_docs)]
+
+use super::{
+    assets,
+    config::{RawAppManifest, RawAppManifestAnyVersion, RawComponentManifest, RawModuleSource},
+};
+use crate::inspect::{inspect, SPIN_HTTP_EXPORT, SPIN_REDIS_EXPORT};
+use anyhow::{anyhow, Context, Result};
+use spin_config::{
+    parse_allowed_network, AllowedHttpHost, ApplicationTrigger, HttpExecutor, RedisExecutor,
+    TriggerConfig,
+};
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+};
+use tokio::fs;
+
+/// How serious a validation finding is.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Severity {
+    /// The application cannot run as configured.
+    Error,
+    /// The application can run, but probably not as intended.
+    Warning,
+}
+
+/// A problem found while validating an application manifest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// The manifest file in which the problem was found.
+    pub file: PathBuf,
+    /// The (1-based) line of the manifest the problem relates to, if known.
+    pub line: Option<usize>,
+    /// A description of the problem.
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match self.line {
+            Some(line) => write!(
+                f,
+                "{}:{}: {}: {}",
+                self.file.display(),
+                line,
+                severity,
+                self.message
+            ),
+            None => write!(f, "{}: {}: {}", self.file.display(), severity, self.message),
+        }
+    }
+}
+
+/// Validates the spin.toml manifest at the given path, returning all the problems
+/// found. An error is returned only if the manifest itself cannot be read.
+pub async fn validate(app: impl AsRef<Path>) -> Result<Vec<Diagnostic>> {
+    let app = app.as_ref();
+    let text = fs::read_to_string(app)
+        .await
+        .with_context(|| anyhow!("Cannot read manifest file from {:?}", app))?;
+    let app_dir = app
+        .parent()
+        .ok_or_else(|| anyhow!("The application file did not have a parent directory."))?;
+
+    let mut validator = Validator {
+        file: app.to_path_buf(),
+        text: &text,
+        diagnostics: vec![],
+    };
+
+    match toml::from_str::<RawAppManifestAnyVersion>(&text) {
+        Ok(RawAppManifestAnyVersion::V1(raw)) => validator.manifest(&raw, app_dir).await,
+        Err(e) => {
+            let line = e.line_col().map(|(line, _)| line + 1);
+            validator.error(line, format!("Invalid manifest: {}", e));
+        }
+    }
+
+    Ok(validator.diagnostics)
+}
+
+struct Validator<'a> {
+    file: PathBuf,
+    text: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Validator<'a> {
+    async fn manifest(&mut self, raw: &RawAppManifest, app_dir: &Path) {
+        let mut ids = HashSet::new();
+        for (idx, c) in raw.components.iter().enumerate() {
+            let line = self.component_line(idx);
+            if !ids.insert(c.id.as_str()) {
+                self.error(line, format!("Duplicate component ID '{}'", c.id));
+            }
+            self.component(c, &raw.info.trigger, app_dir, line).await;
+        }
+    }
+
+    async fn component(
+        &mut self,
+        c: &RawComponentManifest,
+        app_trigger: &ApplicationTrigger,
+        app_dir: &Path,
+        line: Option<usize>,
+    ) {
+        let required_export = self.trigger(c, app_trigger, line);
+
+        match &c.source {
+            RawModuleSource::FileReference(p) => {
+                let path = app_dir.join(p);
+                match fs::read(&path).await {
+                    Ok(bytes) => {
+                        if let Some(export) = required_export {
+                            self.export(c, &bytes, &export, line);
+                        }
+                    }
+                    Err(e) => self.error(
+                        line,
+                        format!(
+                            "Component '{}' module source {} cannot be read: {}",
+                            c.id,
+                            path.display(),
+                            e
+                        ),
+                    ),
+                }
+            }
+            RawModuleSource::Bindle(_) | RawModuleSource::Url(_) => self.warning(
+                line,
+                format!(
+                    "Component '{}' has a remote module source, which cannot be checked locally",
+                    c.id
+                ),
+            ),
+        }
+
+        let exclude_files = c.wasm.exclude_files.clone().unwrap_or_default();
+        for mount in c.wasm.files.iter().flatten() {
+            match assets::collect(std::slice::from_ref(mount), &exclude_files, app_dir) {
+                Ok(files) if files.is_empty() => self.warning(
+                    line,
+                    format!(
+                        "Component '{}' file mount {:?} does not match any files",
+                        c.id, mount
+                    ),
+                ),
+                Ok(_) => {}
+                Err(e) => self.error(
+                    line,
+                    format!(
+                        "Component '{}' file mount {:?} is invalid: {:#}",
+                        c.id, mount, e
+                    ),
+                ),
+            }
+        }
+
+        for host in c.wasm.allowed_http_hosts.iter().flatten() {
+            if let Err(e) = AllowedHttpHost::parse(host) {
+                self.error(
+                    self.line_of(&format!("\"{}\"", host)).or(line),
+                    format!("Component '{}' allowed HTTP host is invalid: {:#}", c.id, e),
+                );
+            }
+        }
+
+        for address in c.wasm.allowed_redis_addresses.iter().flatten() {
+            let valid = matches!(
+                url::Url::parse(address),
+                Ok(u) if matches!(u.scheme(), "redis" | "rediss") && u.host_str().is_some()
+            );
+            if !valid {
+                self.error(
+                    self.line_of(&format!("\"{}\"", address)).or(line),
+                    format!(
+                        "Component '{}' allowed Redis address '{}' is not a valid Redis URL",
+                        c.id, address
+                    ),
+                );
+            }
+        }
+
+        let allowed_networks = c.wasm.outbound_http.iter().flat_map(|o| &o.allowed_networks);
+        for network in allowed_networks {
+            if let Err(e) = parse_allowed_network(network) {
+                self.error(
+                    self.line_of(&format!("\"{}\"", network)).or(line),
+                    format!("Component '{}' allowed network is invalid: {:#}", c.id, e),
+                );
+            }
+        }
+    }
+
+    /// Checks that the component trigger matches the application trigger, and
+    /// returns the export its executor requires.
+    fn trigger(
+        &mut self,
+        c: &RawComponentManifest,
+        app_trigger: &ApplicationTrigger,
+        line: Option<usize>,
+    ) -> Option<String> {
+        match (app_trigger, &c.trigger) {
+            (ApplicationTrigger::Http(_), TriggerConfig::Http(http)) => {
+                match http.executor.clone().unwrap_or_default() {
+                    HttpExecutor::Spin => Some(SPIN_HTTP_EXPORT.to_owned()),
+                    HttpExecutor::Wagi(wagi) => Some(wagi.entrypoint),
+                }
+            }
+            (ApplicationTrigger::Redis(_), TriggerConfig::Redis(redis)) => {
+                match redis.executor.clone().unwrap_or_default() {
+                    RedisExecutor::Spin => Some(SPIN_REDIS_EXPORT.to_owned()),
+                }
+            }
+            (ApplicationTrigger::Http(_), _) => {
+                self.error(
+                    line,
+                    format!(
+                        "Component '{}' must have an HTTP trigger (with a `route`) because the application trigger is HTTP",
+                        c.id
+                    ),
+                );
+                None
+            }
+            (ApplicationTrigger::Redis(_), _) => {
+                self.error(
+                    line,
+                    format!(
+                        "Component '{}' must have a Redis trigger (with a `channel`) because the application trigger is Redis",
+                        c.id
+                    ),
+                );
+                None
+            }
+        }
+    }
+
+    /// Checks that the module exports the function its executor calls.
+    fn export(
+        &mut self,
+        c: &RawComponentManifest,
+        bytes: &[u8],
+        export: &str,
+        line: Option<usize>,
+    ) {
+        match inspect(bytes) {
+            Ok(info) => {
+                if !info.exports(export) {
+                    self.error(
+                        line,
+                        format!(
+                            "Component '{}' module does not export '{}', which its executor requires",
+                            c.id, export
+                        ),
+                    );
+                }
+            }
+            Err(e) => self.error(
+                line,
+                format!(
+                    "Component '{}' module is not a valid WebAssembly module: {}",
+                    c.id, e
+                ),
+            ),
+        }
+    }
+
+    /// Returns the line of the `[[component]]` header for the component at the
+    /// given index.
+    fn component_line(&self, idx: usize) -> Option<usize> {
+        self.text
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| l.trim() == "[[component]]")
+            .nth(idx)
+            .map(|(n, _)| n + 1)
+    }
+
+    /// Returns the first line containing the given text.
+    fn line_of(&self, needle: &str) -> Option<usize> {
+        self.text
+            .lines()
+            .position(|l| l.contains(needle))
+            .map(|n| n + 1)
+    }
+
+    fn error(&mut self, line: Option<usize>, message: String) {
+        self.push(Severity::Error, line, message)
+    }
+
+    fn warning(&mut self, line: Option<usize>, message: String) {
+        self.push(Severity::Warning, line, message)
+    }
+
+    fn push(&mut self, severity: Severity, line: Option<usize>, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            file: self.file.clone(),
+            line,
+            message,
+        })
+    }
+}
@@ -0,0 +1,124 @@
+#![deny(missing_docs)]
+
+use crate::assets::{app_dir, is_under};
+use anyhow::{anyhow, bail, Context, Result};
+use spin_config::DirectoryMount;
+use std::path::{Component, Path};
+use tracing::log;
+
+use super::{
+    assets::is_absolute_guest_path,
+    config::{RawVolume, RawVolumeMode},
+};
+
+/// Prepares the volumes of a component, creating the host directories of
+/// writable volumes if needed. Volumes cannot hide the files of other mounts.
+pub(crate) async fn prepare_component(
+    volumes: &[RawVolume],
+    src: impl AsRef<Path>,
+    app_name: &str,
+    id: &str,
+    file_mounts: &[DirectoryMount],
+) -> Result<Vec<DirectoryMount>> {
+    let mut mounts: Vec<DirectoryMount> = vec![];
+    for volume in volumes {
+        let mount = prepare_volume(volume, &src, app_name, id)
+            .await
+            .with_context(|| {
+                anyhow!("Cannot mount volume at '{}'", volume.destination.display())
+            })?;
+
+        let guest = Path::new(&mount.guest);
+        if let Some(other) = mounts
+            .iter()
+            .find(|m| guest.starts_with(&m.guest) || Path::new(&m.guest).starts_with(guest))
+        {
+            bail!(
+                "Volume at '{}' overlaps volume at '{}'",
+                mount.guest,
+                other.guest
+            );
+        }
+        for files in file_mounts {
+            if let Ok(relative) = guest.strip_prefix(&files.guest) {
+                if files.host.join(relative).exists() {
+                    bail!(
+                        "Volume at '{}' would hide files mounted at '{}'",
+                        mount.guest,
+                        files.guest
+                    );
+                }
+            }
+            if Path::new(&files.guest).starts_with(guest) {
+                bail!(
+                    "Files mounted at '{}' would be inside volume at '{}'",
+                    files.guest,
+                    mount.guest
+                );
+            }
+        }
+        mounts.push(mount);
+    }
+
+    Ok(mounts)
+}
+
+async fn prepare_volume(
+    volume: &RawVolume,
+    src: impl AsRef<Path>,
+    app_name: &str,
+    id: &str,
+) -> Result<DirectoryMount> {
+    let guest = &volume.destination;
+    if !is_absolute_guest_path(guest) {
+        bail!("Guest paths must be absolute");
+    }
+    let relative_guest = guest.strip_prefix("/")?;
+    // The guest path also names the host directory of volumes without a source,
+    // so it must not be able to climb out of the application data directory.
+    if !relative_guest
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        bail!("Guest paths cannot contain '.' or '..' components");
+    }
+
+    let host = match &volume.source {
+        Some(source) => {
+            let host = src.as_ref().join(source);
+            if !source.is_relative() || !is_under(&src, &host) {
+                bail!(
+                    "Source '{}' must be a directory under the application directory",
+                    source.display()
+                );
+            }
+            host
+        }
+        None => app_dir(app_name)?
+            .join("data")
+            .join(id)
+            .join(relative_guest),
+    };
+
+    let read_only = volume.mode == RawVolumeMode::ReadOnly;
+    if !host.is_dir() {
+        if read_only {
+            bail!("Source '{}' is not a directory", host.display());
+        }
+        tokio::fs::create_dir_all(&host)
+            .await
+            .with_context(|| anyhow!("Cannot create directory '{}'", host.display()))?;
+    }
+
+    log::trace!(
+        "Mounting volume '{}' at '{}' ({:?})",
+        host.display(),
+        guest.display(),
+        volume.mode
+    );
+    Ok(DirectoryMount {
+        guest: guest.to_string_lossy().to_string(),
+        host,
+        read_only,
+    })
+}
@@ -0,0 +1,33 @@
+#![deny(missing_docs)]
+
+use crate::assets::create_dir;
+use anyhow::{anyhow, Context, Result};
+use spin_config::DirectoryMount;
+use std::path::Path;
+use tracing::log;
+
+/// Unpacks the archive of files of a component into its asset directory.
+pub(crate) async fn prepare_component(
+    archive: Vec<u8>,
+    base_dst: impl AsRef<Path>,
+    id: &str,
+) -> Result<DirectoryMount> {
+    let host = create_dir(&base_dst, id).await?;
+    log::info!(
+        "Unpacking files for component '{}' to '{}'",
+        id,
+        host.display()
+    );
+
+    let dst = host.clone();
+    tokio::task::spawn_blocking(move || tar::Archive::new(archive.as_slice()).unpack(&dst))
+        .await?
+        .with_context(|| anyhow!("Failed to unpack files for component '{}'", id))?;
+
+    let guest = "/".to_string();
+    Ok(DirectoryMount {
+        guest,
+        host,
+        read_only: false,
+    })
+}
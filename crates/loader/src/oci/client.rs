@@ -0,0 +1,509 @@
+#![deny(missing_docs)]
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::{header, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+use tracing::log;
+
+/// The media type of an OCI image manifest.
+pub const OCI_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+
+const DEFAULT_TAG: &str = "latest";
+
+/// A reference to an artifact in an OCI registry, such as
+/// `localhost:5000/spin/hello:1.0.0` or `ghcr.io/org/app@sha256:...`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reference {
+    /// The registry host, including the port if any.
+    pub registry: String,
+    /// The repository in the registry.
+    pub repository: String,
+    /// The tag or digest of the artifact.
+    pub tag: String,
+}
+
+impl FromStr for Reference {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (registry, rest) = s
+            .split_once('/')
+            .ok_or_else(|| anyhow!("OCI reference '{}' must include a registry", s))?;
+        if !(registry.contains('.') || registry.contains(':') || registry == "localhost") {
+            bail!(
+                "OCI reference '{}' must start with a registry host, such as 'localhost:5000'",
+                s
+            );
+        }
+
+        let (repository, tag) = match rest.split_once('@') {
+            Some((repository, digest)) => (repository, digest),
+            None => match rest.rsplit_once(':') {
+                // A colon after the last slash separates the tag.
+                Some((repository, tag)) if !tag.contains('/') => (repository, tag),
+                _ => (rest, DEFAULT_TAG),
+            },
+        };
+        if repository.is_empty() || tag.is_empty() {
+            bail!("Invalid OCI reference '{}'", s);
+        }
+
+        Ok(Self {
+            registry: registry.to_owned(),
+            repository: repository.to_owned(),
+            tag: tag.to_owned(),
+        })
+    }
+}
+
+impl Display for Reference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.tag.starts_with("sha256:") {
+            write!(f, "{}/{}@{}", self.registry, self.repository, self.tag)
+        } else {
+            write!(f, "{}/{}:{}", self.registry, self.repository, self.tag)
+        }
+    }
+}
+
+/// An OCI image manifest.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OciManifest {
+    /// The manifest schema version. This is always 2.
+    pub schema_version: u32,
+    /// The media type of the manifest.
+    pub media_type: Option<String>,
+    /// The configuration blob of the artifact.
+    pub config: OciDescriptor,
+    /// The layers of the artifact.
+    pub layers: Vec<OciDescriptor>,
+    /// Arbitrary metadata for the artifact.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<BTreeMap<String, String>>,
+}
+
+/// A reference to a blob in an OCI registry.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OciDescriptor {
+    /// The media type of the blob.
+    pub media_type: String,
+    /// The digest of the blob, in the form `sha256:<hex digest>`.
+    pub digest: String,
+    /// The size of the blob in bytes.
+    pub size: u64,
+    /// Arbitrary metadata for the blob.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<BTreeMap<String, String>>,
+}
+
+impl OciDescriptor {
+    /// Creates a descriptor for the given content.
+    pub fn for_content(media_type: impl Into<String>, content: &[u8]) -> Self {
+        Self {
+            media_type: media_type.into(),
+            digest: digest(content),
+            size: content.len() as u64,
+            annotations: None,
+        }
+    }
+}
+
+/// A client for the OCI distribution API.
+/// Registries on the local machine are accessed over HTTP, all others over HTTPS.
+/// Registries requiring a bearer token, even for public artifacts, are sent
+/// an anonymous one.
+#[derive(Clone, Debug, Default)]
+pub struct OciClient {
+    http: reqwest::Client,
+}
+
+impl OciClient {
+    /// Pulls the manifest of an artifact. When the reference is a digest, the
+    /// manifest is verified against it.
+    pub async fn pull_manifest(&self, reference: &Reference) -> Result<OciManifest> {
+        let url = self.url(reference, &format!("manifests/{}", reference.tag));
+        let res = self
+            .send(reference, || {
+                self.http
+                    .get(&url)
+                    .header(header::ACCEPT, OCI_MANIFEST_MEDIA_TYPE)
+            })
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| anyhow!("Failed to pull manifest for {}", reference))?;
+        let bytes = res.bytes().await?;
+        if reference.tag.starts_with("sha256:") {
+            let actual = digest(&bytes);
+            if !actual.eq_ignore_ascii_case(&reference.tag) {
+                bail!("Manifest for {} has digest {}", reference, actual);
+            }
+        }
+        let manifest = serde_json::from_slice(&bytes)
+            .with_context(|| anyhow!("Invalid manifest for {}", reference))?;
+        Ok(manifest)
+    }
+
+    /// Pulls a blob, verifying it against its descriptor.
+    pub async fn pull_blob(&self, reference: &Reference, desc: &OciDescriptor) -> Result<Vec<u8>> {
+        log::trace!("Pulling blob {} from {}", desc.digest, reference);
+        let url = self.url(reference, &format!("blobs/{}", desc.digest));
+        let mut res = self
+            .send(reference, || self.http.get(&url))
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| anyhow!("Failed to pull blob {} from {}", desc.digest, reference))?;
+        let too_large = || {
+            anyhow!(
+                "Blob {} from {} is larger than its size of {} bytes",
+                desc.digest,
+                reference,
+                desc.size
+            )
+        };
+        if matches!(res.content_length(), Some(len) if len > desc.size) {
+            return Err(too_large());
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = res.chunk().await? {
+            if (bytes.len() + chunk.len()) as u64 > desc.size {
+                return Err(too_large());
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        let actual = digest(&bytes);
+        if actual != desc.digest {
+            bail!(
+                "Blob {} from {} has digest {}",
+                desc.digest,
+                reference,
+                actual
+            );
+        }
+        Ok(bytes)
+    }
+
+    /// Pushes a blob, unless the registry already has it, and returns its descriptor.
+    pub async fn push_blob(
+        &self,
+        reference: &Reference,
+        media_type: &str,
+        content: &[u8],
+    ) -> Result<OciDescriptor> {
+        let desc = OciDescriptor::for_content(media_type, content);
+        let digest = &desc.digest;
+        let blob_url = self.url(reference, &format!("blobs/{}", digest));
+        let exists = self.send(reference, || self.http.head(&blob_url)).await?;
+        if exists.status().is_success() {
+            log::trace!("Registry already has blob {}", digest);
+            return Ok(desc);
+        }
+
+        let uploads_url = self.url(reference, "blobs/uploads/");
+        let res = self
+            .send(reference, || self.http.post(&uploads_url))
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| anyhow!("Failed to start blob upload to {}", reference))?;
+        let location = res
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|l| l.to_str().ok())
+            .ok_or_else(|| anyhow!("Registry did not return an upload location"))?;
+        let mut upload_url = match location.starts_with('/') {
+            true => reqwest::Url::parse(&self.base(reference))?.join(location)?,
+            false => reqwest::Url::parse(location)?,
+        };
+        upload_url.query_pairs_mut().append_pair("digest", digest);
+
+        self.send(reference, || {
+            self.http
+                .put(upload_url.clone())
+                .header(header::CONTENT_TYPE, "application/octet-stream")
+                .body(content.to_vec())
+        })
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| anyhow!("Failed to upload blob {} to {}", digest, reference))?;
+
+        Ok(desc)
+    }
+
+    /// Pushes a manifest, tagging it with the tag of the reference.
+    pub async fn push_manifest(&self, reference: &Reference, manifest: &OciManifest) -> Result<()> {
+        let url = self.url(reference, &format!("manifests/{}", reference.tag));
+        let body = serde_json::to_vec(manifest)?;
+        let res = self
+            .send(reference, || {
+                self.http
+                    .put(&url)
+                    .header(header::CONTENT_TYPE, OCI_MANIFEST_MEDIA_TYPE)
+                    .body(body.clone())
+            })
+            .await
+            .with_context(|| anyhow!("Failed to push manifest to {}", reference))?;
+        match res.status() {
+            StatusCode::CREATED | StatusCode::OK => Ok(()),
+            s => bail!("Failed to push manifest to {}: {}", reference, s),
+        }
+    }
+
+    /// Sends a request, and sends it again with an anonymous token if the
+    /// registry answers with a bearer token challenge.
+    async fn send(
+        &self,
+        reference: &Reference,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> reqwest::Result<reqwest::Response> {
+        let res = request().send().await?;
+        if res.status() != StatusCode::UNAUTHORIZED {
+            return Ok(res);
+        }
+        let challenge = match res
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .and_then(|c| c.to_str().ok())
+            .and_then(parse_bearer_challenge)
+        {
+            Some(challenge) => challenge,
+            None => return Ok(res),
+        };
+        match self.anonymous_token(reference, &challenge).await {
+            Ok(token) => request().bearer_auth(token).send().await,
+            Err(e) => {
+                log::debug!("Cannot get an anonymous token for {}: {:#}", reference, e);
+                Ok(res)
+            }
+        }
+    }
+
+    /// Gets an anonymous token from the authorization service of a bearer
+    /// token challenge.
+    async fn anonymous_token(
+        &self,
+        reference: &Reference,
+        challenge: &BTreeMap<String, String>,
+    ) -> Result<String> {
+        let realm = challenge
+            .get("realm")
+            .ok_or_else(|| anyhow!("The challenge has no realm"))?;
+        let scope = match challenge.get("scope") {
+            Some(scope) => scope.clone(),
+            None => format!("repository:{}:pull", reference.repository),
+        };
+        let mut query = vec![("scope", scope.as_str())];
+        if let Some(service) = challenge.get("service") {
+            query.push(("service", service.as_str()));
+        }
+        let res = self
+            .http
+            .get(realm)
+            .query(&query)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())?;
+
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            token: Option<String>,
+            access_token: Option<String>,
+        }
+        let token: TokenResponse = serde_json::from_slice(&res.bytes().await?)?;
+        token
+            .token
+            .or(token.access_token)
+            .ok_or_else(|| anyhow!("The authorization service returned no token"))
+    }
+
+    fn base(&self, reference: &Reference) -> String {
+        let host = reference
+            .registry
+            .rsplit_once(':')
+            .map(|(h, _)| h)
+            .unwrap_or(&reference.registry);
+        let scheme = match host {
+            "localhost" | "127.0.0.1" | "[::1]" => "http",
+            _ => "https",
+        };
+        format!("{}://{}", scheme, reference.registry)
+    }
+
+    fn url(&self, reference: &Reference, path: &str) -> String {
+        format!(
+            "{}/v2/{}/{}",
+            self.base(reference),
+            reference.repository,
+            path
+        )
+    }
+}
+
+/// Parses the parameters of a `WWW-Authenticate` header with the bearer scheme,
+/// such as `Bearer realm="https://ghcr.io/token",service="ghcr.io"`.
+fn parse_bearer_challenge(header: &str) -> Option<BTreeMap<String, String>> {
+    let (scheme, mut rest) = header.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    let mut params = BTreeMap::new();
+    loop {
+        rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        if rest.is_empty() {
+            return Some(params);
+        }
+        let (name, value) = rest.split_once('=')?;
+        let (value, next) = match value.trim_start().strip_prefix('"') {
+            // Quoted values, such as scopes, can contain commas.
+            Some(quoted) => quoted.split_once('"')?,
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+        params.insert(name.trim().to_ascii_lowercase(), value.trim().to_owned());
+        rest = next;
+    }
+}
+
+/// Returns the OCI digest (`sha256:<hex digest>`) of the content.
+pub fn digest(content: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reference() -> Result<()> {
+        let r: Reference = "localhost:5000/spin/hello:1.0.0".parse()?;
+        assert_eq!(r.registry, "localhost:5000");
+        assert_eq!(r.repository, "spin/hello");
+        assert_eq!(r.tag, "1.0.0");
+        assert_eq!(r.to_string(), "localhost:5000/spin/hello:1.0.0");
+
+        let r: Reference = "ghcr.io/fermyon/hello".parse()?;
+        assert_eq!(r.registry, "ghcr.io");
+        assert_eq!(r.repository, "fermyon/hello");
+        assert_eq!(r.tag, "latest");
+
+        let digest = format!("sha256:{}", "a".repeat(64));
+        let r: Reference = format!("ghcr.io/fermyon/hello@{}", digest).parse()?;
+        assert_eq!(r.tag, digest);
+
+        assert!("hello:1.0.0".parse::<Reference>().is_err());
+        assert!("fermyon/hello:1.0.0".parse::<Reference>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_bearer_challenge() {
+        let challenge = parse_bearer_challenge(
+            r#"Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:org/app:pull,push""#,
+        )
+        .unwrap();
+        assert_eq!(challenge["realm"], "https://ghcr.io/token");
+        assert_eq!(challenge["service"], "ghcr.io");
+        assert_eq!(challenge["scope"], "repository:org/app:pull,push");
+
+        let challenge = parse_bearer_challenge("bearer realm=https://example.com").unwrap();
+        assert_eq!(challenge["realm"], "https://example.com");
+
+        assert!(parse_bearer_challenge(r#"Basic realm="registry""#).is_none());
+    }
+
+    /// Starts a registry serving the manifest for every manifest request, and
+    /// the blob for every blob request.
+    fn start_registry(manifest: &'static [u8], blob: &'static [u8]) -> std::net::SocketAddr {
+        use hyper::service::{make_service_fn, service_fn};
+        use std::convert::Infallible;
+
+        let make_svc = make_service_fn(move |_| async move {
+            Ok::<_, Infallible>(service_fn(
+                move |req: hyper::Request<hyper::Body>| async move {
+                    let body = match req.uri().path().contains("/manifests/") {
+                        true => manifest,
+                        false => blob,
+                    };
+                    Ok::<_, Infallible>(hyper::Response::new(hyper::Body::from(body)))
+                },
+            ))
+        });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_svc);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_pulled_content_is_verified() -> Result<()> {
+        const MANIFEST: &[u8] =
+            br#"{"schemaVersion":2,"config":{"mediaType":"a","digest":"b","size":1},"layers":[]}"#;
+        const BLOB: &[u8] = b"blob";
+        let addr = start_registry(MANIFEST, BLOB);
+        let client = OciClient::default();
+
+        let pinned: Reference = format!("{}/spin/hello@{}", addr, digest(MANIFEST)).parse()?;
+        client.pull_manifest(&pinned).await?;
+        let other: Reference = format!("{}/spin/hello@{}", addr, digest(b"other")).parse()?;
+        assert!(client.pull_manifest(&other).await.is_err());
+
+        let desc = OciDescriptor::for_content("a", BLOB);
+        assert_eq!(client.pull_blob(&pinned, &desc).await?, BLOB);
+        let smaller = OciDescriptor {
+            size: 2,
+            ..desc.clone()
+        };
+        assert!(client.pull_blob(&pinned, &smaller).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_token_is_sent() -> Result<()> {
+        use hyper::service::{make_service_fn, service_fn};
+        use std::convert::Infallible;
+
+        // A registry requiring a token, which its authorization service at
+        // `/token` gives to anyone.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let make_svc = make_service_fn(move |_| async move {
+            Ok::<_, Infallible>(service_fn(
+                move |req: hyper::Request<hyper::Body>| async move {
+                    let authorized = req.headers().get("authorization")
+                        == Some(&hyper::header::HeaderValue::from_static("Bearer anonymous"));
+                    let res = if req.uri().path() == "/token" {
+                        assert!(req.uri().query().unwrap().contains("service=registry"));
+                        hyper::Response::new(hyper::Body::from(r#"{"token":"anonymous"}"#))
+                    } else if authorized {
+                        hyper::Response::new(hyper::Body::from("blob"))
+                    } else {
+                        let challenge = format!(
+                            r#"Bearer realm="http://{}/token",service="registry",scope="repository:spin/hello:pull""#,
+                            addr
+                        );
+                        hyper::Response::builder()
+                            .status(401)
+                            .header("www-authenticate", challenge)
+                            .body(hyper::Body::empty())
+                            .unwrap()
+                    };
+                    Ok::<_, Infallible>(res)
+                },
+            ))
+        });
+        tokio::spawn(hyper::Server::from_tcp(listener)?.serve(make_svc));
+
+        let reference: Reference = format!("{}/spin/hello:1.0.0", addr).parse()?;
+        let desc = OciDescriptor::for_content("a", b"blob");
+        assert_eq!(
+            OciClient::default().pull_blob(&reference, &desc).await?,
+            b"blob"
+        );
+
+        Ok(())
+    }
+}
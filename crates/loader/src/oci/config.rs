@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Application configuration stored as the config blob of an OCI artifact.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct RawAppManifest {
+    /// Name of the application.
+    pub name: String,
+    /// Version of the application.
+    pub version: String,
+    /// Description of the application.
+    pub description: Option<String>,
+    /// Authors of the application.
+    pub authors: Option<Vec<String>>,
+    /// Namespace for the application.
+    pub namespace: Option<String>,
+    /// The application trigger.
+    pub trigger: spin_config::ApplicationTrigger,
+
+    /// Key-value stores available to the application components.
+    #[serde(rename = "key_value_store")]
+    pub key_value_stores: Option<Vec<spin_config::KeyValueStoreConfig>>,
+
+    /// Configuration for the application components.
+    #[serde(rename = "component")]
+    pub components: Vec<RawComponentManifest>,
+}
+
+/// Core component configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct RawComponentManifest {
+    /// The digest of the layer containing the module source.
+    pub source: String,
+    /// ID of the component. Used at runtime to select between
+    /// multiple components of the same application.
+    pub id: String,
+    /// Per-component WebAssembly configuration.
+    #[serde(flatten)]
+    pub wasm: RawWasmConfig,
+    /// Trigger configuration.
+    pub trigger: spin_config::TriggerConfig,
+}
+
+/// WebAssembly configuration.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct RawWasmConfig {
+    /// The digest of the layer containing the archive of files to be mapped
+    /// inside the Wasm module at runtime.
+    pub files: Option<String>,
+    /// Optional list of HTTP hosts the component is allowed to connect.
+    pub allowed_http_hosts: Option<Vec<String>>,
+    /// Optional list of Redis server addresses the component is allowed to connect.
+    pub allowed_redis_addresses: Option<Vec<String>>,
+    /// Names of the application key-value stores the component is allowed to use.
+    pub key_value_stores: Option<Vec<String>>,
+    /// Limits of the outbound HTTP requests of the component.
+    pub outbound_http: Option<spin_config::OutboundHttpConfig>,
+    /// Environment variables to be mapped inside the Wasm module at runtime.
+    pub environment: Option<HashMap<String, String>>,
+}
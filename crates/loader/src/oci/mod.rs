@@ -0,0 +1,183 @@
+//! Functionality to get a prepared Spin application from an OCI registry.
+
+#![deny(missing_docs)]
+
+/// Module to prepare the assets for the components of an application.
+mod assets;
+/// Client for the OCI distribution API.
+pub mod client;
+/// Configuration representation for a Spin application in an OCI registry.
+pub mod config;
+
+use crate::{
+    key_value,
+    oci::{
+        client::{OciClient, OciDescriptor, OciManifest, Reference},
+        config::{RawAppManifest, RawComponentManifest},
+    },
+};
+use anyhow::{anyhow, bail, Context, Result};
+use futures::future;
+use spin_config::{
+    parse_allowed_network, AllowedHttpHost, Application, ApplicationInformation, ApplicationOrigin,
+    CoreComponent, ModuleSource, SpinVersion, WasmConfig,
+};
+use std::path::Path;
+use tracing::log;
+
+/// The media type of the config blob of a Spin application artifact.
+pub const SPIN_CONFIG_MEDIA_TYPE: &str = "application/vnd.fermyon.spin.application.config.v1+toml";
+/// The media type of a layer containing a WebAssembly module.
+pub const WASM_LAYER_MEDIA_TYPE: &str = "application/vnd.wasm.content.layer.v1+wasm";
+/// The media type of a layer containing a tar archive of component files.
+pub const ARCHIVE_LAYER_MEDIA_TYPE: &str = "application/vnd.fermyon.spin.archive.layer.v1+tar";
+
+/// Given an OCI reference, pull the artifact, expand its assets locally, and get a
+/// prepared application configuration consumable by a Spin execution context.
+/// If a directory is provided, use it as the base directory to expand the assets,
+/// otherwise create a new temporary directory.
+pub async fn from_oci(
+    reference: &str,
+    base_dst: impl AsRef<Path>,
+) -> Result<Application<CoreComponent>> {
+    let client = OciClient::default();
+    let reference: Reference = reference.parse()?;
+
+    prepare(&client, &reference, base_dst).await
+}
+
+/// Converts an OCI artifact into Spin configuration.
+async fn prepare(
+    client: &OciClient,
+    reference: &Reference,
+    base_dst: impl AsRef<Path>,
+) -> Result<Application<CoreComponent>> {
+    let manifest = client.pull_manifest(reference).await?;
+    if manifest.config.media_type != SPIN_CONFIG_MEDIA_TYPE {
+        bail!(
+            "Artifact {} is not a Spin application: its config has media type '{}'",
+            reference,
+            manifest.config.media_type
+        );
+    }
+
+    let raw: RawAppManifest =
+        toml::from_slice(&client.pull_blob(reference, &manifest.config).await?)
+            .with_context(|| anyhow!("Invalid application config in {}", reference))?;
+    log::trace!("Application config from OCI artifact: {:?}", raw);
+
+    let component_triggers = raw
+        .components
+        .iter()
+        .map(|c| (c.id.clone(), c.trigger.clone()))
+        .collect();
+    let components = future::join_all(
+        raw.components
+            .iter()
+            .cloned()
+            .map(|c| core(c, &manifest, client, reference, &base_dst))
+            .collect::<Vec<_>>(),
+    )
+    .await
+    .into_iter()
+    .collect::<Result<Vec<_>>>()?;
+    let key_value_stores = raw.key_value_stores.clone().unwrap_or_default();
+    let info = info(raw, reference);
+    let key_value_stores = key_value::prepare_stores(key_value_stores, None, &info.name)?;
+    for c in &components {
+        key_value::check_component_stores(&c.id, &c.wasm.key_value_stores, &key_value_stores)?;
+    }
+
+    Ok(Application {
+        info,
+        components,
+        component_triggers,
+        key_value_stores,
+    })
+}
+
+/// Given a raw component manifest, prepare its assets and return a fully formed core component.
+async fn core(
+    raw: RawComponentManifest,
+    manifest: &OciManifest,
+    client: &OciClient,
+    reference: &Reference,
+    base_dst: impl AsRef<Path>,
+) -> Result<CoreComponent> {
+    let layer = find_layer(manifest, &raw.source, WASM_LAYER_MEDIA_TYPE)?;
+    let bytes = client
+        .pull_blob(reference, layer)
+        .await
+        .with_context(|| anyhow!("Cannot get module source for component '{}'", raw.id))?;
+    let source = ModuleSource::Buffer(bytes, format!("layer {}@{}", reference, layer.digest));
+
+    let id = raw.id;
+    let mounts = match &raw.wasm.files {
+        Some(digest) => {
+            let layer = find_layer(manifest, digest, ARCHIVE_LAYER_MEDIA_TYPE)?;
+            let archive = client
+                .pull_blob(reference, layer)
+                .await
+                .with_context(|| anyhow!("Cannot get files for component '{}'", id))?;
+            vec![assets::prepare_component(archive, base_dst, &id).await?]
+        }
+        None => vec![],
+    };
+    let environment = raw.wasm.environment.unwrap_or_default();
+    let allowed_http_hosts = raw.wasm.allowed_http_hosts.unwrap_or_default();
+    AllowedHttpHost::parse_all(&allowed_http_hosts)
+        .with_context(|| anyhow!("Invalid allowed HTTP hosts for component '{}'", id))?;
+    let allowed_redis_addresses = raw.wasm.allowed_redis_addresses.unwrap_or_default();
+    let key_value_stores = raw.wasm.key_value_stores.unwrap_or_default();
+    let outbound_http = raw.wasm.outbound_http.unwrap_or_default();
+    for network in &outbound_http.allowed_networks {
+        parse_allowed_network(network)
+            .with_context(|| anyhow!("Invalid allowed network for component '{}'", id))?;
+    }
+    let wasm = WasmConfig {
+        environment,
+        mounts,
+        allowed_http_hosts,
+        allowed_redis_addresses,
+        key_value_stores,
+        outbound_http,
+    };
+    Ok(CoreComponent { source, id, wasm })
+}
+
+/// Finds the layer with the given digest, checking it has the expected media type.
+fn find_layer<'a>(
+    manifest: &'a OciManifest,
+    digest: &str,
+    media_type: &str,
+) -> Result<&'a OciDescriptor> {
+    let layer = manifest
+        .layers
+        .iter()
+        .find(|l| l.digest == digest)
+        .ok_or_else(|| anyhow!("Artifact does not contain layer {}", digest))?;
+    if layer.media_type != media_type {
+        bail!(
+            "Layer {} has media type '{}', expected '{}'",
+            digest,
+            layer.media_type,
+            media_type
+        );
+    }
+    Ok(layer)
+}
+
+/// Converts the application config from the OCI artifact into the
+/// standard application configuration.
+fn info(raw: RawAppManifest, reference: &Reference) -> ApplicationInformation {
+    ApplicationInformation {
+        spin_version: SpinVersion::V1,
+        name: raw.name,
+        version: raw.version,
+        description: raw.description,
+        authors: raw.authors.unwrap_or_default(),
+        trigger: raw.trigger,
+        namespace: raw.namespace,
+        origin: ApplicationOrigin::Oci(reference.to_string()),
+    }
+}
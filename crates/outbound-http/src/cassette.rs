@@ -0,0 +1,279 @@
+use crate::wasi_outbound_http::{HttpError, Response};
+use anyhow::{Context, Result};
+use http::HeaderMap;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use spin_config::OutboundHttpCassette;
+use std::{path::PathBuf, sync::Mutex};
+
+/// Outbound HTTP requests and their responses, recorded to a cassette file
+/// or replayed from it.
+///
+/// A recorded response is replayed for a request with the same method, URL,
+/// body, and values of the headers selected when recording. Responses are
+/// replayed in the order they were recorded, and the last response recorded
+/// for a request is replayed again once all of them have been.
+pub struct Cassette {
+    path: PathBuf,
+    replay: bool,
+    state: Mutex<State>,
+    /// Held while the file is saved, so that the saves of concurrent requests
+    /// happen one at a time, each with the latest interactions.
+    saving: tokio::sync::Mutex<()>,
+}
+
+struct State {
+    file: CassetteFile,
+    /// Whether each interaction of the file has been replayed.
+    replayed: Vec<bool>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct CassetteFile {
+    /// Lowercase names of the request headers whose values must match.
+    match_headers: Vec<String>,
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+/// The parts of a request matched when replaying.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub(crate) struct RecordedRequest {
+    method: String,
+    url: String,
+    /// Hex-encoded SHA-256 digests of the values of the matched headers, by
+    /// lowercase name, so that credentials are not stored in the file.
+    headers_sha256: Vec<(String, String)>,
+    /// Hex-encoded SHA-256 digest of the body.
+    body_sha256: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct RecordedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    /// Base64-encoded body.
+    body: String,
+}
+
+impl Cassette {
+    /// Opens the cassette file to replay, or creates an empty one to record to.
+    pub fn open(config: &OutboundHttpCassette) -> Result<Self> {
+        let (path, replay, file) = match config {
+            OutboundHttpCassette::Record {
+                path,
+                match_headers,
+            } => {
+                let file = CassetteFile {
+                    match_headers: match_headers.iter().map(|h| h.to_lowercase()).collect(),
+                    interactions: vec![],
+                };
+                (path, false, file)
+            }
+            OutboundHttpCassette::Replay { path } => {
+                let contents = std::fs::read(path)
+                    .with_context(|| format!("Cannot read cassette file {}", path.display()))?;
+                let file = serde_json::from_slice(&contents)
+                    .with_context(|| format!("Invalid cassette file {}", path.display()))?;
+                (path, true, file)
+            }
+        };
+
+        let cassette = Self {
+            path: path.clone(),
+            replay,
+            state: Mutex::new(State {
+                replayed: vec![false; file.interactions.len()],
+                file,
+            }),
+            saving: Default::default(),
+        };
+        if !replay {
+            std::fs::write(path, cassette.contents()?)
+                .with_context(|| format!("Cannot write cassette file {}", path.display()))?;
+        }
+        Ok(cassette)
+    }
+
+    /// Returns whether requests are replayed instead of sent.
+    pub(crate) fn is_replay(&self) -> bool {
+        self.replay
+    }
+
+    /// Returns the parts of a request matched when replaying.
+    pub(crate) fn request(
+        &self,
+        method: &http::Method,
+        url: &Url,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> RecordedRequest {
+        let state = self.state.lock().unwrap();
+        let headers_sha256 = state
+            .file
+            .match_headers
+            .iter()
+            .flat_map(|name| {
+                headers
+                    .get_all(name)
+                    .iter()
+                    .map(move |v| (name.clone(), format!("{:x}", Sha256::digest(v.as_bytes()))))
+            })
+            .collect();
+        RecordedRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers_sha256,
+            body_sha256: format!("{:x}", Sha256::digest(body)),
+        }
+    }
+
+    /// Returns the recorded response to a request.
+    pub(crate) fn replay(&self, request: &RecordedRequest) -> Result<Response, HttpError> {
+        let mut state = self.state.lock().unwrap();
+        let matching = state
+            .file
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| &i.request == request)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        let index = match matching.iter().find(|&&i| !state.replayed[i]) {
+            Some(&index) => index,
+            None => match matching.last() {
+                Some(&index) => index,
+                None => {
+                    tracing::log::info!(
+                        "No response recorded for {} {}",
+                        request.method,
+                        request.url
+                    );
+                    return Err(HttpError::RequestError);
+                }
+            },
+        };
+        state.replayed[index] = true;
+
+        let response = &state.file.interactions[index].response;
+        let body = base64::decode(&response.body).map_err(|e| {
+            tracing::log::error!("Invalid body recorded for {}: {}", request.url, e);
+            HttpError::RuntimeError
+        })?;
+        Ok(Response {
+            status: response.status,
+            headers: Some(response.headers.clone()),
+            body: Some(body),
+        })
+    }
+
+    /// Records the response to a request, and saves the cassette file.
+    pub(crate) async fn record(
+        &self,
+        request: RecordedRequest,
+        response: &Response,
+    ) -> Result<(), HttpError> {
+        let interaction = Interaction {
+            request,
+            response: RecordedResponse {
+                status: response.status,
+                headers: response.headers.clone().unwrap_or_default(),
+                body: base64::encode(response.body.as_deref().unwrap_or_default()),
+            },
+        };
+        self.state
+            .lock()
+            .unwrap()
+            .file
+            .interactions
+            .push(interaction);
+        self.save().await.map_err(|e| {
+            tracing::log::error!("{:#}", e);
+            HttpError::RuntimeError
+        })
+    }
+
+    async fn save(&self) -> Result<()> {
+        let _saving = self.saving.lock().await;
+        tokio::fs::write(&self.path, self.contents()?)
+            .await
+            .with_context(|| format!("Cannot write cassette file {}", self.path.display()))
+    }
+
+    fn contents(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(&self.state.lock().unwrap().file)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(body: &str) -> Response {
+        Response {
+            status: 200,
+            headers: Some(vec![("content-type".to_string(), "text/plain".to_string())]),
+            body: Some(body.as_bytes().to_vec()),
+        }
+    }
+
+    fn headers(authorization: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("Authorization", authorization.parse().unwrap());
+        headers.insert("x-request-id", "1".parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+        let url = Url::parse("https://example.com/data").unwrap();
+        let get = http::Method::GET;
+
+        let recorder = Cassette::open(&OutboundHttpCassette::Record {
+            path: path.clone(),
+            match_headers: vec!["Authorization".to_string()],
+        })
+        .unwrap();
+        for body in ["first", "second"] {
+            let request = recorder.request(&get, &url, &headers("a"), b"");
+            recorder.record(request, &response(body)).await.unwrap();
+        }
+        let request = recorder.request(&get, &url, &headers("b"), b"");
+        recorder.record(request, &response("other")).await.unwrap();
+
+        // Only the digests of the matched header values are stored.
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains(&format!("{:x}", Sha256::digest(b"a"))));
+        assert!(!contents.contains("\"a\""));
+
+        let player = Cassette::open(&OutboundHttpCassette::Replay { path }).unwrap();
+        let replay = |authorization, body: &[u8]| {
+            let request = player.request(&get, &url, &headers(authorization), body);
+            player.replay(&request).map(|r| r.body.unwrap())
+        };
+        assert_eq!(replay("a", b"").unwrap(), b"first");
+        assert_eq!(replay("b", b"").unwrap(), b"other");
+        assert_eq!(replay("a", b"").unwrap(), b"second");
+        assert_eq!(replay("a", b"").unwrap(), b"second");
+        assert!(matches!(replay("c", b""), Err(HttpError::RequestError)));
+        assert!(matches!(replay("a", b"body"), Err(HttpError::RequestError)));
+
+        let request = player.request(&http::Method::POST, &url, &headers("a"), b"");
+        assert!(player.replay(&request).is_err());
+    }
+
+    #[test]
+    fn test_missing_cassette() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing.json");
+        assert!(Cassette::open(&OutboundHttpCassette::Replay { path }).is_err());
+    }
+}
@@ -0,0 +1,535 @@
+//! Host implementation of the `wasi_experimental_http` module imported by the
+//! WASI experimental HTTP library.
+//!
+//! Requests are sent with the client of the component, like those of the
+//! `wasi-outbound-http` interface, so the same allowed hosts, networks,
+//! limits and cassette apply to them.
+
+use crate::{wasi_outbound_http::HttpError, HttpClient};
+use anyhow::{anyhow, Result};
+use http::{
+    header::{HeaderName, HeaderValue},
+    HeaderMap,
+};
+use reqwest::Url;
+use std::{collections::HashMap, str::FromStr};
+use wasmtime::{Caller, Extern, Linker, Memory};
+
+/// The module the experimental HTTP library imports its functions from.
+pub const MODULE: &str = "wasi_experimental_http";
+
+/// The handle of a response kept for the guest until it is closed.
+type ResponseHandle = u32;
+
+/// The error codes of the experimental HTTP library.
+///
+/// Codes from 14 on are specific to Spin, so that guests can tell the limits
+/// of the component apart from other request errors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ErrorCode {
+    InvalidHandle = 1,
+    MemoryNotFound = 2,
+    MemoryAccessError = 3,
+    BufferTooSmall = 4,
+    HeaderNotFound = 5,
+    Utf8Error = 6,
+    DestinationNotAllowed = 7,
+    InvalidMethod = 8,
+    InvalidEncoding = 9,
+    InvalidUrl = 10,
+    RequestError = 11,
+    RuntimeError = 12,
+    TooManySessions = 13,
+    Timeout = 14,
+    ResponseTooLarge = 15,
+    TooManyRedirects = 16,
+}
+
+impl From<HttpError> for ErrorCode {
+    fn from(e: HttpError) -> Self {
+        match e {
+            HttpError::DestinationNotAllowed => Self::DestinationNotAllowed,
+            HttpError::InvalidUrl => Self::InvalidUrl,
+            HttpError::TooManyRequests => Self::TooManySessions,
+            HttpError::RuntimeError => Self::RuntimeError,
+            HttpError::Timeout => Self::Timeout,
+            HttpError::ResponseTooLarge => Self::ResponseTooLarge,
+            HttpError::TooManyRedirects => Self::TooManyRedirects,
+            HttpError::Success | HttpError::RequestError => Self::RequestError,
+        }
+    }
+}
+
+struct Response {
+    headers: HeaderMap,
+    body: Vec<u8>,
+    /// How much of the body the guest has read.
+    pos: usize,
+}
+
+/// The state of the experimental HTTP library for an instance: the client
+/// sending its requests, and the responses it has not closed.
+#[derive(Default)]
+pub struct ExperimentalHttp {
+    client: HttpClient,
+    responses: HashMap<ResponseHandle, Response>,
+    next_handle: ResponseHandle,
+}
+
+impl ExperimentalHttp {
+    pub fn new(client: HttpClient) -> Self {
+        Self {
+            client,
+            responses: HashMap::new(),
+            next_handle: 0,
+        }
+    }
+
+    /// Sends a request, returning the status and the handle of the response.
+    fn request(
+        &mut self,
+        url: &str,
+        method: &str,
+        headers: &str,
+        body: Vec<u8>,
+    ) -> Result<(u16, ResponseHandle), ErrorCode> {
+        let url = Url::parse(url).map_err(|_| ErrorCode::InvalidUrl)?;
+        let method = http::Method::from_str(method).map_err(|_| ErrorCode::InvalidMethod)?;
+        let headers = string_to_header_map(headers).map_err(|_| ErrorCode::InvalidEncoding)?;
+
+        let res = self.client.send_sync(method, url, headers, body)?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in res.headers.unwrap_or_default() {
+            headers.append(
+                HeaderName::from_str(&name).map_err(|_| ErrorCode::RuntimeError)?,
+                HeaderValue::from_str(&value).map_err(|_| ErrorCode::RuntimeError)?,
+            );
+        }
+        let response = Response {
+            headers,
+            body: res.body.unwrap_or_default(),
+            pos: 0,
+        };
+        Ok((res.status, self.insert(response)?))
+    }
+
+    fn insert(&mut self, response: Response) -> Result<ResponseHandle, ErrorCode> {
+        let first = self.next_handle;
+        while self.responses.contains_key(&self.next_handle) {
+            self.next_handle = self.next_handle.wrapping_add(1);
+            if self.next_handle == first {
+                return Err(ErrorCode::TooManySessions);
+            }
+        }
+        let handle = self.next_handle;
+        self.next_handle = self.next_handle.wrapping_add(1);
+        self.responses.insert(handle, response);
+        Ok(handle)
+    }
+
+    fn response(&mut self, handle: ResponseHandle) -> Result<&mut Response, ErrorCode> {
+        self.responses
+            .get_mut(&handle)
+            .ok_or(ErrorCode::InvalidHandle)
+    }
+
+    /// Returns at most `len` of the bytes of the body the guest has not read.
+    fn body_read(&mut self, handle: ResponseHandle, len: u32) -> Result<Vec<u8>, ErrorCode> {
+        let response = self.response(handle)?;
+        let available = (len as usize).min(response.body.len() - response.pos);
+        let chunk = response.body[response.pos..response.pos + available].to_vec();
+        response.pos += available;
+        Ok(chunk)
+    }
+
+    fn header_get(&mut self, handle: ResponseHandle, name: &str) -> Result<Vec<u8>, ErrorCode> {
+        let value = self
+            .response(handle)?
+            .headers
+            .get(name.to_ascii_lowercase())
+            .ok_or(ErrorCode::HeaderNotFound)?;
+        Ok(value.as_bytes().to_vec())
+    }
+
+    fn headers_get_all(&mut self, handle: ResponseHandle) -> Result<String, ErrorCode> {
+        header_map_to_string(&self.response(handle)?.headers).map_err(|_| ErrorCode::RuntimeError)
+    }
+
+    fn close(&mut self, handle: ResponseHandle) {
+        self.responses.remove(&handle);
+    }
+}
+
+/// Adds the functions of the `wasi_experimental_http` module to the linker,
+/// with `get` returning the state of the library from the store data.
+pub fn add_to_linker<T>(
+    linker: &mut Linker<T>,
+    get: impl Fn(&mut T) -> &mut ExperimentalHttp + Send + Sync + Copy + 'static,
+) -> Result<()> {
+    linker.func_wrap(
+        MODULE,
+        "close",
+        move |mut caller: Caller<'_, T>, handle: ResponseHandle| -> u32 {
+            get(caller.data_mut()).close(handle);
+            0
+        },
+    )?;
+
+    linker.func_wrap(
+        MODULE,
+        "body_read",
+        move |mut caller: Caller<'_, T>,
+              handle: ResponseHandle,
+              buf_ptr: u32,
+              buf_len: u32,
+              buf_read_ptr: u32|
+              -> u32 {
+            result_code((|| {
+                let memory = memory(&mut caller)?;
+                let chunk = get(caller.data_mut()).body_read(handle, buf_len)?;
+                write(&memory, &mut caller, buf_ptr, &chunk)?;
+                write_u32(&memory, &mut caller, buf_read_ptr, chunk.len() as u32)
+            })())
+        },
+    )?;
+
+    linker.func_wrap(
+        MODULE,
+        "header_get",
+        move |mut caller: Caller<'_, T>,
+              handle: ResponseHandle,
+              name_ptr: u32,
+              name_len: u32,
+              value_ptr: u32,
+              value_len: u32,
+              value_written_ptr: u32|
+              -> u32 {
+            result_code((|| {
+                let memory = memory(&mut caller)?;
+                let name = read_string(&memory, &caller, name_ptr, name_len)?;
+                let value = get(caller.data_mut()).header_get(handle, &name)?;
+                if value.len() > value_len as usize {
+                    return Err(ErrorCode::BufferTooSmall);
+                }
+                write(&memory, &mut caller, value_ptr, &value)?;
+                write_u32(&memory, &mut caller, value_written_ptr, value.len() as u32)
+            })())
+        },
+    )?;
+
+    linker.func_wrap(
+        MODULE,
+        "headers_get_all",
+        move |mut caller: Caller<'_, T>,
+              handle: ResponseHandle,
+              buf_ptr: u32,
+              buf_len: u32,
+              buf_written_ptr: u32|
+              -> u32 {
+            result_code((|| {
+                let memory = memory(&mut caller)?;
+                let headers = get(caller.data_mut()).headers_get_all(handle)?;
+                if headers.len() > buf_len as usize {
+                    return Err(ErrorCode::BufferTooSmall);
+                }
+                write(&memory, &mut caller, buf_ptr, headers.as_bytes())?;
+                write_u32(&memory, &mut caller, buf_written_ptr, headers.len() as u32)
+            })())
+        },
+    )?;
+
+    linker.func_wrap(
+        MODULE,
+        "req",
+        move |mut caller: Caller<'_, T>,
+              url_ptr: u32,
+              url_len: u32,
+              method_ptr: u32,
+              method_len: u32,
+              headers_ptr: u32,
+              headers_len: u32,
+              body_ptr: u32,
+              body_len: u32,
+              status_code_ptr: u32,
+              res_handle_ptr: u32|
+              -> u32 {
+            result_code((|| {
+                let memory = memory(&mut caller)?;
+                let url = read_string(&memory, &caller, url_ptr, url_len)?;
+                let method = read_string(&memory, &caller, method_ptr, method_len)?;
+                let headers = read_string(&memory, &caller, headers_ptr, headers_len)?;
+                let body = read(&memory, &caller, body_ptr, body_len)?;
+                let (status, handle) =
+                    get(caller.data_mut()).request(&url, &method, &headers, body)?;
+                write(&memory, &mut caller, status_code_ptr, &status.to_le_bytes())?;
+                write_u32(&memory, &mut caller, res_handle_ptr, handle)
+            })())
+        },
+    )?;
+
+    Ok(())
+}
+
+fn result_code(result: Result<(), ErrorCode>) -> u32 {
+    match result {
+        Ok(()) => 0,
+        Err(e) => e as u32,
+    }
+}
+
+/// Returns the memory exported by the guest module.
+fn memory<T>(caller: &mut Caller<'_, T>) -> Result<Memory, ErrorCode> {
+    match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => Ok(memory),
+        _ => Err(ErrorCode::MemoryNotFound),
+    }
+}
+
+fn read<T>(
+    memory: &Memory,
+    caller: &Caller<'_, T>,
+    ptr: u32,
+    len: u32,
+) -> Result<Vec<u8>, ErrorCode> {
+    let start = ptr as usize;
+    memory
+        .data(caller)
+        .get(start..start + len as usize)
+        .map(|bytes| bytes.to_vec())
+        .ok_or(ErrorCode::MemoryAccessError)
+}
+
+fn read_string<T>(
+    memory: &Memory,
+    caller: &Caller<'_, T>,
+    ptr: u32,
+    len: u32,
+) -> Result<String, ErrorCode> {
+    String::from_utf8(read(memory, caller, ptr, len)?).map_err(|_| ErrorCode::Utf8Error)
+}
+
+fn write<T>(
+    memory: &Memory,
+    caller: &mut Caller<'_, T>,
+    ptr: u32,
+    bytes: &[u8],
+) -> Result<(), ErrorCode> {
+    memory
+        .write(caller, ptr as usize, bytes)
+        .map_err(|_| ErrorCode::MemoryAccessError)
+}
+
+fn write_u32<T>(
+    memory: &Memory,
+    caller: &mut Caller<'_, T>,
+    ptr: u32,
+    value: u32,
+) -> Result<(), ErrorCode> {
+    write(memory, caller, ptr, &value.to_le_bytes())
+}
+
+/// Decodes headers sent by the guest, one `name:value` pair per line.
+fn string_to_header_map(s: &str) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for entry in s.lines() {
+        let (name, value) = entry
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid serialized header: [{}]", entry))?;
+        headers.append(HeaderName::from_str(name)?, HeaderValue::from_str(value)?);
+    }
+    Ok(headers)
+}
+
+/// Encodes headers for the guest, one `name:value` pair per line.
+fn header_map_to_string(headers: &HeaderMap) -> Result<String> {
+    let mut res = String::new();
+    for (name, value) in headers {
+        let value = std::str::from_utf8(value.as_bytes())?;
+        anyhow::ensure!(
+            !value.chars().any(|c| c.is_control()),
+            "Invalid header value"
+        );
+        res.push_str(&format!("{}:{}\n", name, value));
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_client;
+
+    #[test]
+    fn test_headers_encoding() {
+        let headers = string_to_header_map("Content-Type:text/plain\nx-a:1\nx-a:2").unwrap();
+        assert_eq!(headers["content-type"], "text/plain");
+        assert_eq!(headers.get_all("x-a").iter().count(), 2);
+        assert_eq!(
+            header_map_to_string(&headers).unwrap(),
+            "content-type:text/plain\nx-a:1\nx-a:2\n"
+        );
+        assert!(string_to_header_map("no separator").is_err());
+    }
+
+    #[test]
+    fn test_disallowed_destinations() {
+        let mut http = ExperimentalHttp::default();
+        assert_eq!(
+            http.request("https://example.com", "GET", "", vec![]),
+            Err(ErrorCode::DestinationNotAllowed)
+        );
+        assert_eq!(
+            http.request("not a url", "GET", "", vec![]),
+            Err(ErrorCode::InvalidUrl)
+        );
+        assert_eq!(http.body_read(0, 1), Err(ErrorCode::InvalidHandle));
+    }
+
+    /// A guest sending a GET request to the URL it is given, and reading the
+    /// response body.
+    const GUEST: &str = r#"
+        (module
+          (import "wasi_experimental_http" "req"
+            (func $req (param i32 i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+          (import "wasi_experimental_http" "body_read"
+            (func $body_read (param i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 1024) "GET")
+          ;; The URL is written at 0, the status at 2048, the handle at 2052,
+          ;; the body length at 2056 and the body at 4096.
+          (func (export "send") (param $url_len i32) (result i32)
+            (call $req
+              (i32.const 0) (local.get $url_len) (i32.const 1024) (i32.const 3)
+              (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0)
+              (i32.const 2048) (i32.const 2052)))
+          (func (export "read") (result i32)
+            (call $body_read
+              (i32.load (i32.const 2052)) (i32.const 4096) (i32.const 1024) (i32.const 2056))))
+    "#;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_guest_requests() -> Result<()> {
+        use wasmtime::{Engine, Instance, Module, Store};
+
+        let (addr, client) = test_client(Default::default(), |_| async {
+            hyper::Response::new(hyper::Body::from("hello"))
+        });
+
+        let engine = Engine::default();
+        let mut linker = Linker::new(&engine);
+        add_to_linker(&mut linker, |http| http)?;
+        let mut store = Store::new(&engine, ExperimentalHttp::new(client));
+        let module = Module::new(&engine, GUEST)?;
+        let instance: Instance = linker.instantiate(&mut store, &module)?;
+        let memory = instance.get_memory(&mut store, "memory").unwrap();
+        let send = instance.get_typed_func::<u32, u32, _>(&mut store, "send")?;
+        let read = instance.get_typed_func::<(), u32, _>(&mut store, "read")?;
+
+        let url = format!("http://{}/", addr);
+        memory.write(&mut store, 0, url.as_bytes())?;
+        assert_eq!(send.call(&mut store, url.len() as u32)?, 0);
+        assert_eq!(memory.data(&store)[2048..2050], 200u16.to_le_bytes());
+        assert_eq!(read.call(&mut store, ())?, 0);
+        assert_eq!(memory.data(&store)[2056..2060], 5u32.to_le_bytes());
+        assert_eq!(&memory.data(&store)[4096..4101], b"hello");
+
+        // The host of the URL must be allowed, even if it resolves to the
+        // same address.
+        let url = format!("http://localhost:{}/", addr.port());
+        memory.write(&mut store, 0, url.as_bytes())?;
+        assert_eq!(
+            send.call(&mut store, url.len() as u32)?,
+            ErrorCode::DestinationNotAllowed as u32
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_limit_errors() -> Result<()> {
+        use spin_config::OutboundHttpConfig;
+        use std::time::Duration;
+
+        let config = OutboundHttpConfig {
+            timeout_ms: Some(100),
+            max_response_bytes: Some(1),
+            max_redirects: Some(1),
+            ..Default::default()
+        };
+        let (addr, client) = test_client(config, |req| async move {
+            match req.uri().path() {
+                "/slow" => {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    hyper::Response::new(hyper::Body::empty())
+                }
+                "/redirect" => hyper::Response::builder()
+                    .status(302)
+                    .header("location", "/redirect")
+                    .body(hyper::Body::empty())
+                    .unwrap(),
+                _ => hyper::Response::new(hyper::Body::from("hello")),
+            }
+        });
+        let mut http = ExperimentalHttp::new(client);
+        let mut request = |path: &str| {
+            http.request(&format!("http://{}{}", addr, path), "GET", "", vec![])
+                .map(|_| ())
+        };
+
+        assert_eq!(request("/slow"), Err(ErrorCode::Timeout));
+        assert_eq!(request("/"), Err(ErrorCode::ResponseTooLarge));
+        assert_eq!(request("/redirect"), Err(ErrorCode::TooManyRedirects));
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_requests_are_recorded_and_replayed() -> Result<()> {
+        use crate::Cassette;
+        use spin_config::OutboundHttpCassette;
+        use std::sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        };
+
+        // Only the first response is recorded, so that later responses can
+        // only be replayed.
+        let recorded = Arc::new(AtomicBool::new(false));
+        let (addr, client) = test_client(Default::default(), move |_| {
+            let body = match recorded.swap(true, Ordering::SeqCst) {
+                false => "recorded",
+                true => "live",
+            };
+            async move { hyper::Response::new(hyper::Body::from(body)) }
+        });
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("cassette.json");
+        let http = |cassette: OutboundHttpCassette| -> Result<ExperimentalHttp> {
+            let cassette = Arc::new(Cassette::open(&cassette)?);
+            Ok(ExperimentalHttp::new(
+                client.clone().with_cassette(Some(cassette)),
+            ))
+        };
+        let url = format!("http://{}/", addr);
+
+        let mut recorder = http(OutboundHttpCassette::Record {
+            path: path.clone(),
+            match_headers: vec![],
+        })?;
+        let (status, handle) = recorder.request(&url, "GET", "", vec![]).unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(recorder.body_read(handle, 1024).unwrap(), b"recorded");
+
+        let mut player = http(OutboundHttpCassette::Replay { path })?;
+        let (status, handle) = player.request(&url, "GET", "", vec![]).unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(player.body_read(handle, 1024).unwrap(), b"recorded");
+        assert_eq!(
+            player.request(&url, "POST", "", vec![]),
+            Err(ErrorCode::RequestError)
+        );
+
+        Ok(())
+    }
+}
@@ -1,10 +1,26 @@
-use futures::executor::block_on;
+mod cassette;
+pub mod experimental;
+mod networks;
+
 use http::HeaderMap;
-use reqwest::{Client, Url};
-use std::str::FromStr;
-use tokio::runtime::Handle;
+use networks::{is_caused_by, DestinationDenied, PolicyResolver};
+use reqwest::{redirect::Policy, Client, Url};
+use spin_config::{AllowedHttpHost, OutboundHttpConfig};
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    runtime::{Handle, RuntimeFlavor},
+    sync::{OwnedSemaphorePermit, Semaphore},
+};
 use wasi_outbound_http::*;
 
+pub use cassette::Cassette;
+pub use experimental::ExperimentalHttp;
+pub use networks::NetworkPolicy;
 pub use wasi_outbound_http::add_to_linker;
 
 wit_bindgen_wasmtime::export!("../../wit/ephemeral/wasi-outbound-http.wit");
@@ -12,126 +28,334 @@ wit_bindgen_wasmtime::export!("../../wit/ephemeral/wasi-outbound-http.wit");
 /// A very simple implementation for outbound HTTP requests.
 #[derive(Default, Clone)]
 pub struct OutboundHttp {
-    /// List of hosts guest modules are allowed to make requests to.
-    pub allowed_hosts: Option<Vec<String>>,
+    /// The client sending the requests, to the destinations the guest module
+    /// is allowed to make requests to.
+    client: HttpClient,
 }
 
 impl OutboundHttp {
-    pub fn new(allowed_hosts: Option<Vec<String>>) -> Self {
-        Self { allowed_hosts }
-    }
-
-    /// Check if guest module is allowed to send request to URL, based on the list of
-    /// allowed hosts defined by the runtime.
-    /// If `None` is passed, the guest module is not allowed to send the request.
-    fn is_allowed(url: &str, allowed_hosts: Option<Vec<String>>) -> Result<bool, HttpError> {
-        let url_host = Url::parse(url)
-            .map_err(|_| HttpError::InvalidUrl)?
-            .host_str()
-            .ok_or(HttpError::InvalidUrl)?
-            .to_owned();
-        match allowed_hosts.as_deref() {
-            Some(domains) => {
-                let allowed: Result<Vec<_>, _> = domains.iter().map(|d| Url::parse(d)).collect();
-                let allowed = allowed.map_err(|_| HttpError::InvalidUrl)?;
-
-                Ok(allowed
-                    .iter()
-                    .map(|u| u.host_str().unwrap())
-                    .any(|x| x == url_host.as_str()))
-            }
-            None => Ok(false),
-        }
+    pub fn new(client: HttpClient) -> Self {
+        Self { client }
     }
 }
 
 impl wasi_outbound_http::WasiOutboundHttp for OutboundHttp {
     fn request(&mut self, req: Request) -> Result<Response, HttpError> {
-        if !Self::is_allowed(req.uri, self.allowed_hosts.clone())? {
-            tracing::log::info!("Destination not allowed: {}", req.uri);
-            return Err(HttpError::DestinationNotAllowed);
-        }
-
-        let method = http::Method::from(req.method);
         let url = Url::parse(req.uri).map_err(|_| HttpError::InvalidUrl)?;
+        let method = http::Method::from(req.method);
         let headers = request_headers(req.headers)?;
         let body = req.body.unwrap_or_default().to_vec();
+        self.client.send_sync(method, url, headers, body)
+    }
+}
+
+/// Client for the outbound HTTP requests of a component, applying the limits
+/// configured for the component, and refusing to reach the hosts and networks
+/// the component is not allowed to, including through redirects.
+///
+/// Clones of a client share the same connection pool and the same count of
+/// requests being sent, so a client shared by all the instances of a component
+/// avoids a new connection and TLS handshake per request.
+///
+/// Requests from guests block the calling thread, so guests should be called
+/// on a blocking thread, as the triggers do with `spawn_blocking`. Pooled
+/// connections are only reused on a multi-threaded runtime.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: Client,
+    config: OutboundHttpConfig,
+    /// Destinations the component is allowed to make requests to.
+    allowed_hosts: Arc<Vec<AllowedHttpHost>>,
+    networks: NetworkPolicy,
+    /// Permits for the requests being sent, if their number is limited.
+    permits: Option<Arc<Semaphore>>,
+    /// Cassette the requests are recorded to or replayed from, if any.
+    cassette: Option<Arc<Cassette>>,
+}
 
+impl HttpClient {
+    /// Creates a client sending requests to the allowed hosts only, and
+    /// applying the given limits.
+    pub fn new(
+        config: OutboundHttpConfig,
+        allowed_hosts: Vec<AllowedHttpHost>,
+    ) -> anyhow::Result<Self> {
+        let allowed_hosts = Arc::new(allowed_hosts);
+        let networks = NetworkPolicy::new(&config.allowed_networks)?;
+        let client = build_client(&config, &allowed_hosts, &networks)?;
+        let permits = config
+            .max_concurrent_requests
+            .map(|n| Arc::new(Semaphore::new(n as usize)));
+
+        Ok(Self {
+            client,
+            config,
+            allowed_hosts,
+            networks,
+            permits,
+            cassette: None,
+        })
+    }
+
+    /// Records the requests and their responses to the cassette, or replays
+    /// the recorded responses instead of sending the requests.
+    pub fn with_cassette(mut self, cassette: Option<Arc<Cassette>>) -> Self {
+        self.cassette = cassette;
+        self
+    }
+
+    /// Returns whether the component is allowed to send requests to the URL.
+    fn allows_host(&self, url: &Url) -> bool {
+        allows_host(&self.allowed_hosts, url)
+    }
+
+    /// Reserves one of the requests the component is allowed to send at the
+    /// same time, which is released when the returned permit is dropped.
+    fn permit(&self) -> Result<Option<OwnedSemaphorePermit>, HttpError> {
+        match &self.permits {
+            Some(permits) => match permits.clone().try_acquire_owned() {
+                Ok(permit) => Ok(Some(permit)),
+                Err(_) => {
+                    tracing::log::info!("Too many outbound requests being sent");
+                    Err(HttpError::TooManyRequests)
+                }
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Sends a request to a destination the component is allowed to reach
+    /// and reads the entire response, blocking the current thread.
+    fn send_sync(
+        &self,
+        method: http::Method,
+        url: Url,
+        headers: HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<Response, HttpError> {
+        if !self.allows_host(&url) {
+            tracing::log::info!("Destination not allowed: {}", url);
+            return Err(HttpError::DestinationNotAllowed);
+        }
+        if !self.networks.allows_url(&url) {
+            tracing::log::info!("Destination in a denied network: {}", url);
+            return Err(HttpError::DestinationNotAllowed);
+        }
+
+        let _permit = self.permit()?;
         match Handle::try_current() {
-            // If running in a Tokio runtime, spawn a new blocking executor
-            // that will send the HTTP request, and block on its execution.
-            // This attempts to avoid any deadlocks from other operations
-            // already executing on the same executor (compared with just
-            // blocking on the current one).
-            Ok(r) => block_on(r.spawn_blocking(move || -> Result<Response, HttpError> {
-                let client = Client::builder().build().unwrap();
-                let res = block_on(
-                    client
-                        .request(method, url)
-                        .headers(headers)
-                        .body(body)
-                        .send(),
-                )?;
-
-                Response::try_from(res)
-            }))
-            .map_err(|_| HttpError::RuntimeError)?,
-            Err(_) => {
-                let res = reqwest::blocking::Client::new()
-                    .request(method, url)
-                    .headers(headers)
-                    .body(body)
-                    .send()?;
-                Ok(Response::try_from(res)?)
+            // If running in a multi-threaded Tokio runtime, send the request
+            // with the shared client on that runtime, so that its pooled
+            // connections are reused. `block_in_place` has no effect on the
+            // blocking threads guests are called on, but lets the runtime move
+            // other tasks off the current thread if it is a worker thread.
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(|| {
+                    handle.block_on(self.send(method, url, headers, body))
+                })
+            }
+            // A current-thread runtime cannot be blocked on from a thread
+            // running it, so the request is sent from a thread of its own.
+            Ok(_) => {
+                let client = self.clone();
+                std::thread::spawn(move || client.send_blocking(method, url, headers, body))
+                    .join()
+                    .map_err(|_| HttpError::RuntimeError)?
             }
+            Err(_) => self.send_blocking(method, url, headers, body),
         }
     }
-}
 
-impl From<Method> for http::Method {
-    fn from(m: Method) -> Self {
-        match m {
-            Method::Get => http::Method::GET,
-            Method::Post => http::Method::POST,
-            Method::Put => http::Method::PUT,
-            Method::Delete => http::Method::DELETE,
-            Method::Patch => http::Method::PATCH,
-            Method::Head => http::Method::HEAD,
-            Method::Options => http::Method::OPTIONS,
+    /// Sends a request and reads the entire response, which are recorded to
+    /// the cassette if any. When replaying, returns the recorded response
+    /// instead.
+    async fn send(
+        &self,
+        method: http::Method,
+        url: Url,
+        headers: HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<Response, HttpError> {
+        let cassette = match &self.cassette {
+            Some(cassette) => cassette,
+            None => return self.send_request(method, url, headers, body).await,
+        };
+        let request = cassette.request(&method, &url, &headers, &body);
+        if cassette.is_replay() {
+            return cassette.replay(&request);
         }
+        let res = self.send_request(method, url, headers, body).await?;
+        cassette.record(request, &res).await?;
+        Ok(res)
     }
-}
-
-impl TryFrom<reqwest::Response> for Response {
-    type Error = HttpError;
 
-    fn try_from(res: reqwest::Response) -> Result<Self, Self::Error> {
+    async fn send_request(
+        &self,
+        method: http::Method,
+        url: Url,
+        headers: HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<Response, HttpError> {
+        let mut res = self
+            .client
+            .request(method, url)
+            .headers(headers)
+            .body(body)
+            .send()
+            .await?;
+        self.check_content_length(res.content_length())?;
         let status = res.status().as_u16();
         let headers = response_headers(res.headers())?;
 
-        let body = Some(block_on(res.bytes())?.to_vec());
+        let mut body = Vec::new();
+        while let Some(chunk) = res.chunk().await? {
+            self.check_response_size(body.len() + chunk.len())?;
+            body.extend_from_slice(&chunk);
+        }
 
         Ok(Response {
             status,
             headers,
-            body,
+            body: Some(body),
         })
     }
+
+    /// Sends a request and reads the entire response, outside of a Tokio runtime.
+    ///
+    /// The request is sent on a runtime of its own, with a client of its own,
+    /// since pooled connections are bound to the runtime that opened them.
+    fn send_blocking(
+        &self,
+        method: http::Method,
+        url: Url,
+        headers: HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<Response, HttpError> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|_| HttpError::RuntimeError)?;
+        let client = Self {
+            client: build_client(&self.config, &self.allowed_hosts, &self.networks)?,
+            ..self.clone()
+        };
+        runtime.block_on(client.send(method, url, headers, body))
+    }
+
+    fn check_content_length(&self, len: Option<u64>) -> Result<(), HttpError> {
+        match (len, self.config.max_response_bytes) {
+            (Some(len), Some(max)) if len > max => Err(HttpError::ResponseTooLarge),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_response_size(&self, len: usize) -> Result<(), HttpError> {
+        match self.config.max_response_bytes {
+            Some(max) if len as u64 > max => Err(HttpError::ResponseTooLarge),
+            _ => Ok(()),
+        }
+    }
 }
 
-impl TryFrom<reqwest::blocking::Response> for Response {
-    type Error = HttpError;
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new(Default::default(), vec![]).expect("failed to create the default HTTP client")
+    }
+}
 
-    fn try_from(res: reqwest::blocking::Response) -> Result<Self, Self::Error> {
-        let status = res.status().as_u16();
-        let headers = response_headers(res.headers())?;
-        let body = Some(res.bytes()?.to_vec());
+/// The number of redirects followed if not configured, as by default in `reqwest`.
+const DEFAULT_MAX_REDIRECTS: usize = 10;
 
-        Ok(Response {
-            status,
-            headers,
-            body,
-        })
+fn build_client(
+    config: &OutboundHttpConfig,
+    allowed_hosts: &Arc<Vec<AllowedHttpHost>>,
+    networks: &NetworkPolicy,
+) -> reqwest::Result<Client> {
+    let mut builder = Client::builder()
+        .redirect(redirect_policy(
+            config,
+            allowed_hosts.clone(),
+            networks.clone(),
+        ))
+        .dns_resolver(Arc::new(PolicyResolver(networks.clone())))
+        // A proxy would resolve the destination itself, bypassing the check
+        // of the resolved addresses against the denied networks.
+        .no_proxy();
+    if let Some(timeout) = connect_timeout(config) {
+        builder = builder.connect_timeout(timeout);
+    }
+    if let Some(timeout) = timeout(config) {
+        builder = builder.timeout(timeout);
+    }
+    builder.build()
+}
+
+/// Follows redirects up to the configured limit, unless they lead to a host
+/// the component is not allowed to send requests to, or to an IP address in a
+/// denied network. Host names are checked against the networks when resolved.
+fn redirect_policy(
+    config: &OutboundHttpConfig,
+    allowed_hosts: Arc<Vec<AllowedHttpHost>>,
+    networks: NetworkPolicy,
+) -> Policy {
+    let max_redirects = config
+        .max_redirects
+        .map_or(DEFAULT_MAX_REDIRECTS, |max| max as usize);
+    Policy::custom(move |attempt| {
+        if max_redirects == 0 {
+            attempt.stop()
+        } else if attempt.previous().len() > max_redirects {
+            attempt.error("too many redirects")
+        } else if !allows_host(&allowed_hosts, attempt.url()) {
+            let url = attempt.url().to_string();
+            attempt.error(HostNotAllowed(url))
+        } else if !networks.allows_url(attempt.url()) {
+            let url = attempt.url().to_string();
+            attempt.error(DestinationDenied(url))
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// Check if guest module is allowed to send request to URL, based on the list of
+/// allowed hosts defined by the runtime.
+fn allows_host(allowed_hosts: &[AllowedHttpHost], url: &Url) -> bool {
+    allowed_hosts.iter().any(|h| h.allows(url))
+}
+
+/// The error of a redirect to a host the component is not allowed to send
+/// requests to.
+#[derive(Debug)]
+struct HostNotAllowed(String);
+
+impl Display for HostNotAllowed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is not an allowed host", self.0)
+    }
+}
+
+impl std::error::Error for HostNotAllowed {}
+
+fn connect_timeout(config: &OutboundHttpConfig) -> Option<Duration> {
+    config.connect_timeout_ms.map(Duration::from_millis)
+}
+
+fn timeout(config: &OutboundHttpConfig) -> Option<Duration> {
+    config.timeout_ms.map(Duration::from_millis)
+}
+
+impl From<Method> for http::Method {
+    fn from(m: Method) -> Self {
+        match m {
+            Method::Get => http::Method::GET,
+            Method::Post => http::Method::POST,
+            Method::Put => http::Method::PUT,
+            Method::Delete => http::Method::DELETE,
+            Method::Patch => http::Method::PATCH,
+            Method::Head => http::Method::HEAD,
+            Method::Options => http::Method::OPTIONS,
+        }
     }
 }
 
@@ -166,7 +390,151 @@ impl From<anyhow::Error> for HttpError {
 }
 
 impl From<reqwest::Error> for HttpError {
-    fn from(_: reqwest::Error) -> Self {
-        Self::RequestError
+    fn from(e: reqwest::Error) -> Self {
+        if is_caused_by::<HostNotAllowed>(&e) {
+            tracing::log::info!("Redirect destination not allowed: {}", e);
+            Self::DestinationNotAllowed
+        } else if is_caused_by::<DestinationDenied>(&e) {
+            tracing::log::info!("Destination in a denied network: {}", e);
+            Self::DestinationNotAllowed
+        } else if e.is_timeout() {
+            Self::Timeout
+        } else if e.is_redirect() {
+            Self::TooManyRedirects
+        } else {
+            Self::RequestError
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{future::Future, net::SocketAddr};
+
+    #[test]
+    fn test_limits() {
+        let client = HttpClient::new(
+            OutboundHttpConfig {
+                max_response_bytes: Some(4),
+                max_concurrent_requests: Some(1),
+                ..Default::default()
+            },
+            vec![],
+        )
+        .unwrap();
+
+        assert!(client.check_content_length(None).is_ok());
+        assert!(client.check_content_length(Some(4)).is_ok());
+        assert!(matches!(
+            client.check_content_length(Some(5)),
+            Err(HttpError::ResponseTooLarge)
+        ));
+        assert!(matches!(
+            client.check_response_size(5),
+            Err(HttpError::ResponseTooLarge)
+        ));
+
+        // Clones of a client share the count of requests being sent.
+        let permit = client.permit().unwrap();
+        assert!(matches!(
+            client.clone().permit(),
+            Err(HttpError::TooManyRequests)
+        ));
+        drop(permit);
+        assert!(client.clone().permit().is_ok());
+
+        let unlimited = HttpClient::default();
+        assert!(unlimited.check_content_length(Some(u64::MAX)).is_ok());
+        assert!(unlimited.permit().unwrap().is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_redirects_to_disallowed_hosts_fail() {
+        // Redirects `/<host>` to the same server through the given host name.
+        let (addr, client) = test_client(
+            OutboundHttpConfig {
+                allowed_networks: vec!["::1".to_string()],
+                ..Default::default()
+            },
+            |req| async move {
+                let host = req.headers()[http::header::HOST].to_str().unwrap();
+                let port = host.rsplit(':').next().unwrap().to_owned();
+                match req.uri().path().trim_start_matches('/') {
+                    "" => hyper::Response::new(hyper::Body::from("done")),
+                    host => hyper::Response::builder()
+                        .status(302)
+                        .header(http::header::LOCATION, format!("http://{}:{}/", host, port))
+                        .body(hyper::Body::empty())
+                        .unwrap(),
+                }
+            },
+        );
+        let send = |path: &str| {
+            let url = Url::parse(&format!("http://{}/{}", addr, path)).unwrap();
+            client.send(http::Method::GET, url, HeaderMap::new(), vec![])
+        };
+
+        let res = send("127.0.0.1").await.unwrap();
+        assert_eq!(res.body.unwrap(), b"done");
+        assert!(matches!(
+            send("localhost").await,
+            Err(HttpError::DestinationNotAllowed)
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_requests_on_current_thread_runtime() {
+        let (addr, client) = test_client(Default::default(), |_| async {
+            hyper::Response::new(hyper::Body::from("done"))
+        });
+        let url = Url::parse(&format!("http://{}/", addr)).unwrap();
+
+        // Sends the request from a task of a current-thread runtime, as a guest
+        // called without `spawn_blocking` would.
+        let res = std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async {
+                client.send_sync(http::Method::GET, url, HeaderMap::new(), vec![])
+            })
+        })
+        .join()
+        .unwrap()
+        .unwrap();
+        assert_eq!(res.body.unwrap(), b"done");
+    }
+
+    /// Starts a server answering requests with the handler, and returns its
+    /// address and a client allowed to send requests to it.
+    pub(crate) fn test_client<F, Fut>(
+        mut config: OutboundHttpConfig,
+        handler: F,
+    ) -> (SocketAddr, HttpClient)
+    where
+        F: Fn(hyper::Request<hyper::Body>) -> Fut + Clone + Send + Sync + 'static,
+        Fut: Future<Output = hyper::Response<hyper::Body>> + Send + 'static,
+    {
+        use hyper::service::{make_service_fn, service_fn};
+        use std::convert::Infallible;
+
+        let make_svc = make_service_fn(move |_| {
+            let handler = handler.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    let res = handler(req);
+                    async move { Ok::<_, Infallible>(res.await) }
+                }))
+            }
+        });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_svc);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        config.allowed_networks.push("127.0.0.1".to_string());
+        let allowed_hosts = vec![AllowedHttpHost::parse(&format!("http://{}", addr)).unwrap()];
+        (addr, HttpClient::new(config, allowed_hosts).unwrap())
     }
 }
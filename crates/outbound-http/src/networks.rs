@@ -0,0 +1,173 @@
+use anyhow::Result;
+use hyper::client::connect::dns::Name;
+use ipnet::IpNet;
+use reqwest::{
+    dns::{Addrs, Resolve, Resolving},
+    Url,
+};
+use spin_config::parse_allowed_network;
+use std::{
+    fmt::{self, Display, Formatter},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+use url::Host;
+
+/// Networks outbound requests cannot reach unless the component allows them:
+/// the loopback, link-local and private networks, and the unspecified
+/// addresses, which usually reach the local host.
+const DENIED_NETWORKS: &[&str] = &[
+    "0.0.0.0/8",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "10.0.0.0/8",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+    "::/128",
+    "::1/128",
+    "fe80::/10",
+    "fc00::/7",
+];
+
+/// The IP addresses the outbound requests of a component can reach.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkPolicy {
+    /// Networks the component is allowed to reach despite being denied.
+    allowed: Vec<IpNet>,
+}
+
+impl NetworkPolicy {
+    /// Creates a policy from the `allowed_networks` of a component.
+    pub fn new(allowed_networks: &[String]) -> Result<Self> {
+        let allowed = allowed_networks
+            .iter()
+            .map(|n| parse_allowed_network(n))
+            .collect::<Result<_>>()?;
+        Ok(Self { allowed })
+    }
+
+    /// Returns whether requests can reach the address.
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(ip) => ipv4_mapped(&ip).map_or(IpAddr::V6(ip), IpAddr::V4),
+            ip => ip,
+        };
+        !denied_networks().any(|n| n.contains(&ip)) || self.allowed.iter().any(|n| n.contains(&ip))
+    }
+
+    /// Returns whether requests can reach the host of the URL, if it is an
+    /// IP address. Host names are checked when they are resolved.
+    pub(crate) fn allows_url(&self, url: &Url) -> bool {
+        match url.host() {
+            Some(Host::Ipv4(ip)) => self.allows(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => self.allows(IpAddr::V6(ip)),
+            _ => true,
+        }
+    }
+}
+
+fn denied_networks() -> impl Iterator<Item = IpNet> {
+    DENIED_NETWORKS.iter().map(|n| n.parse().unwrap())
+}
+
+/// Returns the IPv4 address of an IPv4-mapped IPv6 address, such as `::ffff:127.0.0.1`.
+fn ipv4_mapped(ip: &Ipv6Addr) -> Option<Ipv4Addr> {
+    match ip.octets() {
+        [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, a, b, c, d] => Some(Ipv4Addr::new(a, b, c, d)),
+        _ => None,
+    }
+}
+
+/// Resolves host names to the addresses allowed by a network policy, so
+/// that requests cannot reach denied addresses through DNS.
+pub(crate) struct PolicyResolver(pub NetworkPolicy);
+
+impl Resolve for PolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.0.clone();
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .collect::<Vec<SocketAddr>>();
+            let allowed = addrs
+                .iter()
+                .filter(|a| policy.allows(a.ip()))
+                .copied()
+                .collect::<Vec<_>>();
+            if allowed.is_empty() && !addrs.is_empty() {
+                return Err(DestinationDenied(name.as_str().to_owned()).into());
+            }
+            Ok(Box::new(allowed.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// The error of a request to a destination in a denied network.
+#[derive(Debug)]
+pub(crate) struct DestinationDenied(pub String);
+
+impl Display for DestinationDenied {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is in a denied network", self.0)
+    }
+}
+
+impl std::error::Error for DestinationDenied {}
+
+/// Returns whether the error is caused by an error of type `E`, such as a
+/// destination in a denied network.
+pub(crate) fn is_caused_by<E: std::error::Error + 'static>(
+    e: &(dyn std::error::Error + 'static),
+) -> bool {
+    let mut source = Some(e);
+    while let Some(e) = source {
+        if e.is::<E>() {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_denied_networks() {
+        let policy = NetworkPolicy::default();
+        for denied in [
+            "127.0.0.1",
+            "169.254.169.254",
+            "10.1.2.3",
+            "172.31.0.1",
+            "192.168.1.1",
+            "0.0.0.0",
+            "::1",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!policy.allows(ip(denied)), "{}", denied);
+        }
+        for allowed in ["93.184.216.34", "172.32.0.1", "2606:2800:220:1::1"] {
+            assert!(policy.allows(ip(allowed)), "{}", allowed);
+        }
+        assert!(!policy.allows_url(&Url::parse("http://127.0.0.1:3000").unwrap()));
+        assert!(!policy.allows_url(&Url::parse("http://[::1]").unwrap()));
+        assert!(policy.allows_url(&Url::parse("http://localhost").unwrap()));
+    }
+
+    #[test]
+    fn test_allowed_networks() {
+        let policy =
+            NetworkPolicy::new(&["127.0.0.1".to_string(), "10.0.0.0/8".to_string()]).unwrap();
+        assert!(policy.allows(ip("127.0.0.1")));
+        assert!(policy.allows(ip("10.1.2.3")));
+        assert!(!policy.allows(ip("127.0.0.2")));
+        assert!(!policy.allows(ip("192.168.1.1")));
+    }
+}
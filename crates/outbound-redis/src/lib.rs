@@ -0,0 +1,115 @@
+use outbound_redis::*;
+use redis::{Client, Commands, Connection};
+use std::collections::HashMap;
+use url::Url;
+
+pub use outbound_redis::add_to_linker;
+
+wit_bindgen_wasmtime::export!("../../wit/ephemeral/outbound-redis.wit");
+
+/// The default port of Redis servers.
+const DEFAULT_REDIS_PORT: u16 = 6379;
+
+/// A simple implementation of outbound Redis operations.
+#[derive(Default)]
+pub struct OutboundRedis {
+    /// List of Redis server addresses guest modules are allowed to connect to.
+    pub allowed_addresses: Vec<String>,
+    /// Connections opened by the guest module, by address.
+    connections: HashMap<String, Connection>,
+}
+
+impl OutboundRedis {
+    pub fn new(allowed_addresses: Vec<String>) -> Self {
+        Self {
+            allowed_addresses,
+            connections: HashMap::new(),
+        }
+    }
+
+    /// Check if guest module is allowed to connect to the Redis server at the
+    /// given address, based on the list of allowed addresses defined by the runtime.
+    /// Addresses match if they have the same scheme, host and port.
+    fn is_allowed(address: &str, allowed_addresses: &[String]) -> bool {
+        let address = match server(address) {
+            Some(a) => a,
+            None => return false,
+        };
+        allowed_addresses
+            .iter()
+            .filter_map(|a| server(a))
+            .any(|a| a == address)
+    }
+
+    /// Returns the connection to the Redis server at the given address,
+    /// connecting to it the first time it is used.
+    fn connection(&mut self, address: &str) -> Result<&mut Connection, Error> {
+        if !Self::is_allowed(address, &self.allowed_addresses) {
+            tracing::log::info!("Redis address not allowed: {}", address);
+            return Err(Error::Error);
+        }
+        if !self.connections.contains_key(address) {
+            let connection = Client::open(address)
+                .and_then(|c| c.get_connection())
+                .map_err(log_error)?;
+            self.connections.insert(address.to_owned(), connection);
+        }
+        Ok(self.connections.get_mut(address).unwrap())
+    }
+}
+
+impl outbound_redis::OutboundRedis for OutboundRedis {
+    fn publish(&mut self, address: &str, channel: &str, payload: &[u8]) -> Result<(), Error> {
+        self.connection(address)?
+            .publish(channel, payload)
+            .map_err(log_error)
+    }
+
+    fn get(&mut self, address: &str, key: &str) -> Result<Vec<u8>, Error> {
+        self.connection(address)?.get(key).map_err(log_error)
+    }
+
+    fn set(&mut self, address: &str, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.connection(address)?.set(key, value).map_err(log_error)
+    }
+
+    fn incr(&mut self, address: &str, key: &str) -> Result<i64, Error> {
+        self.connection(address)?.incr(key, 1).map_err(log_error)
+    }
+}
+
+/// Returns the scheme, host and port of a Redis server address.
+fn server(address: &str) -> Option<(String, String, u16)> {
+    let url = Url::parse(address).ok()?;
+    let host = url.host_str()?.to_owned();
+    let port = url.port().unwrap_or(DEFAULT_REDIS_PORT);
+    Some((url.scheme().to_owned(), host, port))
+}
+
+fn log_error(e: redis::RedisError) -> Error {
+    tracing::log::error!("Outbound Redis error: {}", e);
+    Error::Error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowed_addresses() {
+        let allowed = vec!["redis://localhost".to_owned()];
+        assert!(OutboundRedis::is_allowed("redis://localhost", &allowed));
+        assert!(OutboundRedis::is_allowed(
+            "redis://localhost:6379/1",
+            &allowed
+        ));
+        assert!(!OutboundRedis::is_allowed(
+            "redis://localhost:6380",
+            &allowed
+        ));
+        assert!(!OutboundRedis::is_allowed("rediss://localhost", &allowed));
+        assert!(!OutboundRedis::is_allowed("redis://example.com", &allowed));
+        assert!(!OutboundRedis::is_allowed("not a url", &allowed));
+        assert!(!OutboundRedis::is_allowed("redis://localhost", &[]));
+    }
+}
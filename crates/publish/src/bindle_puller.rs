@@ -0,0 +1,285 @@
+#![deny(missing_docs)]
+
+use anyhow::{anyhow, bail, Context, Result};
+use bindle::{Id, Invoice, Label};
+use spin_loader::{
+    bindle::{config as bindle_schema, verify_parcel, SPIN_MANIFEST_MEDIA_TYPE},
+    local::config as local_schema,
+};
+use std::path::{Component, Path, PathBuf};
+
+type BindleClient = bindle::client::Client<spin_loader::bindle::BindleTokenManager>;
+
+const WASM_MEDIA_TYPE: &str = "application/wasm";
+const MODULES_DIR: &str = "modules";
+const FILES_DIR: &str = "files";
+
+/// Pulls a bindle and all its parcels from a Bindle server, and writes them
+/// out as a standalone bindle.
+pub async fn pull_all(
+    client: &BindleClient,
+    bindle_id: &Id,
+    dest_dir: impl AsRef<Path>,
+) -> Result<()> {
+    let invoice = client
+        .get_invoice(bindle_id)
+        .await
+        .with_context(|| anyhow!("Failed to fetch invoice '{}'", bindle_id))?;
+
+    let bindle_dir = dest_dir.as_ref().join(bindle_id.sha());
+    let parcels_dir = bindle_dir.join("parcels");
+    tokio::fs::create_dir_all(&parcels_dir)
+        .await
+        .with_context(|| anyhow!("Failed to create directory '{}'", parcels_dir.display()))?;
+
+    let invoice_file = bindle_dir.join("invoice.toml");
+    tokio::fs::write(&invoice_file, toml::to_string_pretty(&invoice)?)
+        .await
+        .with_context(|| format!("Failed to write invoice to '{}'", invoice_file.display()))?;
+
+    for label in labels(&invoice) {
+        let bytes = client
+            .get_parcel(bindle_id, &label.sha256)
+            .await
+            .with_context(|| anyhow!("Failed to fetch parcel {}@{}", bindle_id, label.sha256))?;
+        verify_parcel(label, &bytes)?;
+        let parcel_file = parcels_dir.join(format!("{}.dat", label.sha256));
+        tokio::fs::write(&parcel_file, &bytes)
+            .await
+            .with_context(|| format!("Failed to write parcel to '{}'", parcel_file.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Reconstitutes a runnable application from a standalone bindle: writes a
+/// spin.toml manifest to the application directory, with the Wasm modules
+/// under `modules` and the files of each component under `files/<component ID>`.
+pub async fn export_app(
+    standalone_dir: impl AsRef<Path>,
+    bindle_id: &Id,
+    app_dir: impl AsRef<Path>,
+) -> Result<PathBuf> {
+    let bindle_dir = standalone_dir.as_ref().join(bindle_id.sha());
+    let parcels_dir = bindle_dir.join("parcels");
+    let app_dir = app_dir.as_ref();
+
+    let invoice_file = bindle_dir.join("invoice.toml");
+    let invoice: Invoice = toml::from_slice(
+        &tokio::fs::read(&invoice_file)
+            .await
+            .with_context(|| anyhow!("Failed to read invoice '{}'", invoice_file.display()))?,
+    )
+    .with_context(|| anyhow!("Failed to parse invoice '{}'", invoice_file.display()))?;
+    let parcel_file = |label: &Label| parcels_dir.join(format!("{}.dat", label.sha256));
+    let annotation = |key: &str| {
+        invoice
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(key))
+            .cloned()
+    };
+
+    let manifest_label = labels(&invoice)
+        .find(|l| l.media_type == SPIN_MANIFEST_MEDIA_TYPE)
+        .ok_or_else(|| anyhow!("Invoice does not contain a Spin manifest"))?;
+    let manifest: bindle_schema::RawAppManifest =
+        toml::from_slice(&tokio::fs::read(parcel_file(manifest_label)).await?)
+            .context("Failed to parse the Spin manifest parcel")?;
+
+    let mut components = vec![];
+    for c in manifest.components {
+        // Prefer the original module path, if it is safe to write to.
+        let module_label = labels(&invoice)
+            .find(|l| l.sha256 == c.source && l.media_type == WASM_MEDIA_TYPE)
+            .or_else(|| labels(&invoice).find(|l| l.sha256 == c.source))
+            .ok_or_else(|| anyhow!("Invoice does not contain module of component '{}'", c.id))?;
+        let module_path = match is_safe_relative(&module_label.name) {
+            true => Path::new(MODULES_DIR).join(&module_label.name),
+            false => Path::new(MODULES_DIR).join(format!("{}.wasm", c.source)),
+        };
+        copy_parcel(&parcel_file(module_label), &app_dir.join(&module_path)).await?;
+
+        let files = match &c.wasm.files {
+            Some(group) => {
+                let files_path = Path::new(FILES_DIR).join(&c.id);
+                if !is_safe_relative(&c.id) {
+                    bail!("Cannot export files of component with ID '{}'", c.id);
+                }
+                for parcel in invoice.parcel.iter().flatten() {
+                    let member_of = parcel
+                        .conditions
+                        .as_ref()
+                        .and_then(|c| c.member_of.as_ref());
+                    if !member_of.map(|m| m.contains(group)).unwrap_or(false) {
+                        continue;
+                    }
+                    if !is_safe_relative(&parcel.label.name) {
+                        bail!("Cannot export file with path '{}'", parcel.label.name);
+                    }
+                    let dest = app_dir.join(&files_path).join(&parcel.label.name);
+                    copy_parcel(&parcel_file(&parcel.label), &dest).await?;
+                }
+                Some(vec![local_schema::RawFileMount::Placement(
+                    local_schema::RawDirectoryPlacement {
+                        source: files_path,
+                        destination: PathBuf::from("/"),
+                    },
+                )])
+            }
+            None => None,
+        };
+
+        components.push(local_schema::RawComponentManifest {
+            source: local_schema::RawModuleSource::FileReference(module_path),
+            id: c.id,
+            wasm: local_schema::RawWasmConfig {
+                environment: c.wasm.environment,
+                files,
+                exclude_files: None,
+                volumes: None,
+                allowed_http_hosts: c.wasm.allowed_http_hosts,
+                allowed_redis_addresses: c.wasm.allowed_redis_addresses,
+                key_value_stores: c.wasm.key_value_stores,
+                outbound_http: c.wasm.outbound_http,
+            },
+            trigger: c.trigger,
+            build: None,
+        });
+    }
+
+    let local = local_schema::RawAppManifestAnyVersion::V1(local_schema::RawAppManifest {
+        info: local_schema::RawAppInformation {
+            name: bindle_id.name().to_owned(),
+            version: annotation(bindle_schema::VERSION_ANNOTATION)
+                .unwrap_or_else(|| bindle_id.version_string()),
+            description: invoice.bindle.description.clone(),
+            authors: invoice.bindle.authors.clone(),
+            trigger: manifest.trigger,
+            namespace: annotation(bindle_schema::NAMESPACE_ANNOTATION),
+        },
+        key_value_stores: manifest.key_value_stores,
+        components,
+    });
+    // Going through a TOML value orders the simple values before the tables.
+    let text = toml::to_string_pretty(&toml::Value::try_from(&local)?)
+        .context("Failed to write app manifest to TOML")?;
+    let app_file = app_dir.join("spin.toml");
+    tokio::fs::write(&app_file, text)
+        .await
+        .with_context(|| format!("Failed to write manifest to '{}'", app_file.display()))?;
+
+    Ok(app_file)
+}
+
+fn labels(invoice: &Invoice) -> impl Iterator<Item = &Label> {
+    invoice.parcel.iter().flatten().map(|p| &p.label)
+}
+
+async fn copy_parcel(parcel_file: &Path, dest: &Path) -> Result<()> {
+    if let Some(parent) = dest.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::copy(parcel_file, dest).await.with_context(|| {
+        format!(
+            "Failed to copy parcel from '{}' to '{}'",
+            parcel_file.display(),
+            dest.display()
+        )
+    })?;
+    Ok(())
+}
+
+/// Checks that a path from the invoice cannot escape the directory it is written to.
+fn is_safe_relative(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Body, Request, Response, Server, StatusCode,
+    };
+    use std::{convert::Infallible, net::SocketAddr};
+
+    const TEST_APP_DIR: &str = "../loader/tests/valid-with-files";
+
+    async fn prepare_standalone(staging_dir: &Path) -> Result<Id> {
+        let app_file = Path::new(TEST_APP_DIR).join("spin.toml");
+        let (invoice, sources) = crate::expand_manifest(&app_file, None, staging_dir).await?;
+        crate::write(TEST_APP_DIR, staging_dir, &invoice, &sources).await?;
+        Ok(invoice.bindle.id)
+    }
+
+    /// Serves a standalone bindle as a Bindle server would.
+    fn serve_bindle(bindle_dir: PathBuf) -> SocketAddr {
+        let make_svc = make_service_fn(move |_| {
+            let bindle_dir = bindle_dir.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                    let path = match req.uri().path().split_once('@') {
+                        Some((_, sha)) => bindle_dir.join("parcels").join(format!("{}.dat", sha)),
+                        None => bindle_dir.join("invoice.toml"),
+                    };
+                    async move {
+                        let res = match std::fs::read(path) {
+                            Ok(bytes) => Response::new(Body::from(bytes)),
+                            Err(_) => Response::builder()
+                                .status(StatusCode::NOT_FOUND)
+                                .body(Body::empty())
+                                .unwrap(),
+                        };
+                        Ok::<_, Infallible>(res)
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_svc);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_pull_and_export() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let staging_dir = temp_dir.path().join("staging");
+        let pull_dir = temp_dir.path().join("pulled");
+        let app_dir = temp_dir.path().join("app");
+
+        let id = prepare_standalone(&staging_dir).await?;
+        let addr = serve_bindle(staging_dir.join(id.sha()));
+        let client = BindleClient::new(
+            &format!("http://{}/v1", addr),
+            spin_loader::bindle::BindleTokenManager::NoToken(bindle::client::tokens::NoToken),
+        )?;
+
+        pull_all(&client, &id, &pull_dir).await?;
+        assert!(pull_dir.join(id.sha()).join("invoice.toml").is_file());
+
+        let app_file = export_app(&pull_dir, &id, &app_dir).await?;
+        assert!(app_dir.join("modules/spin-fs.wasm").is_file());
+        assert_eq!(
+            std::fs::read(app_dir.join("files/fs/static/numbers/1"))?,
+            std::fs::read(Path::new(TEST_APP_DIR).join("static/numbers/1"))?
+        );
+
+        // The exported manifest is runnable.
+        let app = spin_loader::from_file(&app_file, temp_dir.path().join("assets")).await?;
+        assert_eq!(app.info.name, "spin-local-source-test");
+        assert_eq!(app.components[0].id, "fs");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsafe_paths_are_rejected() {
+        assert!(is_safe_relative("static/numbers/1"));
+        assert!(!is_safe_relative("../escape"));
+        assert!(!is_safe_relative("/etc/passwd"));
+    }
+}
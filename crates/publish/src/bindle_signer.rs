@@ -0,0 +1,125 @@
+#![deny(missing_docs)]
+
+use anyhow::{anyhow, Context, Result};
+use bindle::{
+    invoice::{
+        sign,
+        signature::{KeyEntry, KeyRing, SecretKeyEntry, SecretKeyFile, SignatureRole},
+        Signed,
+    },
+    Invoice,
+};
+use std::{convert::TryFrom, path::Path};
+
+/// Signs an invoice as its creator, using the first key with the creator role
+/// in the secret keys file.
+pub async fn sign_invoice(invoice: Invoice, secret_keys_file: impl AsRef<Path>) -> Result<Invoice> {
+    let path = secret_keys_file.as_ref();
+    let keys = SecretKeyFile::load_file(path)
+        .await
+        .with_context(|| anyhow!("Cannot read secret keys from {}", path.display()))?;
+    let key = keys
+        .key
+        .iter()
+        .find(|k| k.roles.contains(&SignatureRole::Creator))
+        .ok_or_else(|| anyhow!("{} contains no key with the creator role", path.display()))?;
+
+    let signed = sign(invoice, vec![(SignatureRole::Creator, key)])
+        .map_err(|e| anyhow!("{}", e))
+        .context("Failed to sign invoice")?;
+    Ok(signed.signed())
+}
+
+/// Creates a signing keypair with the creator role and adds it to the secret
+/// keys file, creating the file if needed. If a trusted keys file is given, the
+/// public key is also added to it, so that bindles signed with the new key can
+/// be verified.
+pub async fn create_key(
+    label: &str,
+    secret_keys_file: impl AsRef<Path>,
+    trusted_keys_file: Option<&Path>,
+) -> Result<()> {
+    let secret_path = secret_keys_file.as_ref();
+    let mut keys = match secret_path.is_file() {
+        true => SecretKeyFile::load_file(secret_path)
+            .await
+            .with_context(|| anyhow!("Cannot read secret keys from {}", secret_path.display()))?,
+        false => SecretKeyFile::default(),
+    };
+    let secret = SecretKeyEntry::new(label.to_owned(), vec![SignatureRole::Creator]);
+    let public = KeyEntry::try_from(secret.clone())
+        .map_err(|e| anyhow!("{}", e))
+        .context("Failed to derive the public key")?;
+
+    keys.key.push(secret);
+    keys.save_file(secret_path)
+        .await
+        .with_context(|| anyhow!("Cannot write secret keys to {}", secret_path.display()))?;
+
+    if let Some(trusted_path) = trusted_keys_file {
+        let mut keyring: KeyRing = match trusted_path.is_file() {
+            true => toml::from_slice(&tokio::fs::read(trusted_path).await?)
+                .with_context(|| anyhow!("Invalid trusted keys file {}", trusted_path.display()))?,
+            false => KeyRing::new(vec![]),
+        };
+        keyring.key.push(public);
+        if let Some(parent) = trusted_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(trusted_path, toml::to_string_pretty(&keyring)?)
+            .await
+            .with_context(|| anyhow!("Cannot write trusted keys to {}", trusted_path.display()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spin_loader::bindle::SignaturePolicy;
+    use std::path::PathBuf;
+
+    const TEST_APP_DIR: &str = "../loader/tests/valid-with-files";
+
+    #[tokio::test]
+    async fn test_signed_bindle_requires_trusted_key() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let secret_keys = temp_dir.path().join("secret-keys.toml");
+        let trusted_keys = temp_dir.path().join("trusted-keys.toml");
+        let staging_dir = temp_dir.path().join("staging");
+        create_key("spin-test", &secret_keys, Some(&trusted_keys)).await?;
+
+        let app_file = PathBuf::from(TEST_APP_DIR).join("spin.toml");
+        let (invoice, sources) = crate::expand_manifest(&app_file, None, &staging_dir).await?;
+        let invoice = sign_invoice(invoice, &secret_keys).await?;
+        crate::write(TEST_APP_DIR, &staging_dir, &invoice, &sources).await?;
+        let id = invoice.bindle.id.to_string();
+
+        let policy = SignaturePolicy::new(true, Some(&trusted_keys)).await?;
+        let app = spin_loader::from_standalone_bindle(
+            &id,
+            &staging_dir,
+            &policy,
+            temp_dir.path().join("assets"),
+        )
+        .await?;
+        assert_eq!(app.components[0].id, "fs");
+
+        // A key that is not trusted is rejected.
+        let other_secret_keys = temp_dir.path().join("other-secret-keys.toml");
+        let other_trusted_keys = temp_dir.path().join("other-trusted-keys.toml");
+        create_key("other", &other_secret_keys, Some(&other_trusted_keys)).await?;
+        let policy = SignaturePolicy::new(true, Some(&other_trusted_keys)).await?;
+        assert!(spin_loader::from_standalone_bindle(
+            &id,
+            &staging_dir,
+            &policy,
+            temp_dir.path().join("assets"),
+        )
+        .await
+        .is_err());
+
+        Ok(())
+    }
+}
@@ -156,3 +156,96 @@ fn copy_parcel_failed_msg(source_file: &Path, dest_file: &Path) -> String {
         dest_file.display()
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_APP_DIR: &str = "../loader/tests/valid-with-files";
+
+    #[tokio::test]
+    async fn test_standalone_bindle_runs_offline() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let staging_dir = temp_dir.path().join("staging");
+        let assets_dir = temp_dir.path().join("assets");
+
+        let app_file = Path::new(TEST_APP_DIR).join("spin.toml");
+        let (invoice, sources) = crate::expand_manifest(&app_file, None, &staging_dir).await?;
+        write(TEST_APP_DIR, &staging_dir, &invoice, &sources).await?;
+
+        let id = invoice.bindle.id.to_string();
+        let app = spin_loader::from_standalone_bindle(
+            &id,
+            &staging_dir,
+            &Default::default(),
+            &assets_dir,
+        )
+        .await?;
+        assert_eq!(app.info.name, "spin-local-source-test");
+        assert_eq!(app.components.len(), 1);
+
+        let component = &app.components[0];
+        assert_eq!(component.id, "fs");
+        assert!(component.wasm.mounts[0]
+            .host
+            .join("static/numbers/1")
+            .is_file());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_application_information_is_preserved() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let app_dir = temp_dir.path().join("app");
+        let staging_dir = temp_dir.path().join("staging");
+        std::fs::create_dir_all(&app_dir)?;
+        std::fs::copy(
+            Path::new(TEST_APP_DIR).join("spin-fs.wasm"),
+            app_dir.join("spin-fs.wasm"),
+        )?;
+        let app_file = app_dir.join("spin.toml");
+        std::fs::write(
+            &app_file,
+            r#"
+            spin_version = "1"
+            name = "spin-info-test"
+            version = "1.2.3"
+            description = "An application with all its information"
+            authors = ["Fermyon Engineering <engineering@fermyon.com>"]
+            namespace = "spin-tests"
+            trigger = { type = "http", base = "/" }
+
+            [[component]]
+            id = "fs"
+            source = "spin-fs.wasm"
+            [component.trigger]
+            route = "/..."
+            "#,
+        )?;
+
+        let buildinfo = semver::BuildMetadata::new("ci.1")?;
+        let (invoice, sources) =
+            crate::expand_manifest(&app_file, Some(buildinfo), &staging_dir).await?;
+        write(&app_dir, &staging_dir, &invoice, &sources).await?;
+
+        let from_file = spin_loader::from_file(&app_file, temp_dir.path().join("a")).await?;
+        let from_bindle = spin_loader::from_standalone_bindle(
+            &invoice.bindle.id.to_string(),
+            &staging_dir,
+            &Default::default(),
+            temp_dir.path().join("b"),
+        )
+        .await?;
+
+        let (expected, actual) = (from_file.info, from_bindle.info);
+        assert_eq!(actual.spin_version, expected.spin_version);
+        assert_eq!(actual.name, expected.name);
+        assert_eq!(actual.version, "1.2.3");
+        assert_eq!(actual.description, expected.description);
+        assert_eq!(actual.authors, expected.authors);
+        assert_eq!(actual.namespace.as_deref(), Some("spin-tests"));
+
+        Ok(())
+    }
+}
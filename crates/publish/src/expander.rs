@@ -7,7 +7,13 @@ use path_absolutize::Absolutize;
 use semver::BuildMetadata;
 use sha2::{Digest, Sha256};
 use spin_loader::{bindle::config as bindle_schema, local::config as local_schema};
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// The version of the Spin manifest format that is published.
+const SPIN_MANIFEST_VERSION: &str = "1";
 
 /// Expands a file-based application manifest to a Bindle invoice.
 pub async fn expand_manifest(
@@ -30,6 +36,7 @@ pub async fn expand_manifest(
 
     // * create an invoice where
     //   - the metadata is copied from the app manifest
+    //   - the metadata the bindle spec has no room for is kept in annotations
     //   - there is a group for each component
     //   - there is a parcel for each asset
     //   - there is a parcel for each module source
@@ -64,7 +71,7 @@ pub async fn expand_manifest(
             description: manifest.info.description.clone(),
             authors: manifest.info.authors.clone(),
         },
-        annotations: None,
+        annotations: Some(app_annotations(&manifest.info)),
         parcel: Some(parcels),
         group: Some(groups),
         signature: None,
@@ -84,18 +91,41 @@ fn bindle_manifest(
         .map(|c| bindle_component_manifest(c, base_dir))
         .collect::<Result<Vec<_>>>()
         .context("Failed to convert components to Bindle format")?;
-    let trigger = local.info.trigger.clone();
+    let info = &local.info;
 
     Ok(bindle_schema::RawAppManifest {
-        trigger,
+        trigger: info.trigger.clone(),
+        key_value_stores: local.key_value_stores.clone(),
         components,
     })
 }
 
+fn app_annotations(info: &local_schema::RawAppInformation) -> BTreeMap<String, String> {
+    let mut annotations = BTreeMap::new();
+    annotations.insert(
+        bindle_schema::SPIN_VERSION_ANNOTATION.to_owned(),
+        SPIN_MANIFEST_VERSION.to_owned(),
+    );
+    annotations.insert(
+        bindle_schema::VERSION_ANNOTATION.to_owned(),
+        info.version.clone(),
+    );
+    if let Some(namespace) = &info.namespace {
+        annotations.insert(
+            bindle_schema::NAMESPACE_ANNOTATION.to_owned(),
+            namespace.clone(),
+        );
+    }
+    annotations
+}
+
 fn bindle_component_manifest(
     local: &local_schema::RawComponentManifest,
     base_dir: &Path,
 ) -> Result<bindle_schema::RawComponentManifest> {
+    if local.wasm.volumes.is_some() {
+        anyhow::bail!("This version of Spin can't publish components with volumes")
+    }
     let source_digest = match &local.source {
         local_schema::RawModuleSource::FileReference(path) => {
             let full_path = base_dir.join(path);
@@ -107,6 +137,9 @@ fn bindle_component_manifest(
                 "This version of Spin can't publish components whose sources are already bindles"
             )
         }
+        local_schema::RawModuleSource::Url(_) => {
+            anyhow::bail!("This version of Spin can't publish components whose sources are URLs")
+        }
     };
     let asset_group = local.wasm.files.as_ref().map(|_| group_name_for(&local.id));
     Ok(bindle_schema::RawComponentManifest {
@@ -116,6 +149,9 @@ fn bindle_component_manifest(
             environment: local.wasm.environment.clone(),
             files: asset_group,
             allowed_http_hosts: local.wasm.allowed_http_hosts.clone(),
+            allowed_redis_addresses: local.wasm.allowed_redis_addresses.clone(),
+            key_value_stores: local.wasm.key_value_stores.clone(),
+            outbound_http: local.wasm.outbound_http.clone(),
         },
         trigger: local.trigger.clone(),
     })
@@ -141,6 +177,9 @@ async fn wasm_parcel(
                 "This version of Spin can't publish components whose sources are already bindles"
             )
         }
+        local_schema::RawModuleSource::Url(_) => {
+            anyhow::bail!("This version of Spin can't publish components whose sources are URLs")
+        }
     };
     let absolute_wasm_file = base_dir.join(wasm_file);
 
@@ -170,7 +209,8 @@ fn collect_assets(
     base_dir: impl AsRef<Path>,
 ) -> Result<Vec<(spin_loader::local::assets::FileMount, String)>> {
     let patterns = component.wasm.files.clone().unwrap_or_default();
-    let file_mounts = spin_loader::local::assets::collect(&patterns, &base_dir)
+    let exclude_files = component.wasm.exclude_files.clone().unwrap_or_default();
+    let file_mounts = spin_loader::local::assets::collect(&patterns, &exclude_files, &base_dir)
         .with_context(|| format!("Failed to get file mounts for component '{}'", component.id))?;
     let annotated = file_mounts
         .into_iter()
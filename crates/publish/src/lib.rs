@@ -1,11 +1,17 @@
 #![deny(missing_docs)]
 
-//! Functions for publishing Spin applications to Bindle.
+//! Functions for publishing Spin applications to Bindle and OCI registries.
 
+mod bindle_puller;
 mod bindle_pusher;
+mod bindle_signer;
 mod bindle_writer;
 mod expander;
+mod oci_pusher;
 
+pub use bindle_puller::{export_app, pull_all};
 pub use bindle_pusher::push_all;
+pub use bindle_signer::{create_key, sign_invoice};
 pub use bindle_writer::write;
 pub use expander::expand_manifest;
+pub use oci_pusher::push_oci;
@@ -0,0 +1,314 @@
+#![deny(missing_docs)]
+
+use anyhow::{anyhow, bail, Context, Result};
+use path_absolutize::Absolutize;
+use spin_loader::{
+    local::{assets::FileMount, config as local_schema},
+    oci::{
+        client::{OciClient, OciDescriptor, OciManifest, Reference, OCI_MANIFEST_MEDIA_TYPE},
+        config as oci_schema, ARCHIVE_LAYER_MEDIA_TYPE, SPIN_CONFIG_MEDIA_TYPE,
+        WASM_LAYER_MEDIA_TYPE,
+    },
+};
+use std::path::Path;
+
+/// Pushes a file-based application to an OCI registry, tagging it with the
+/// tag of the reference. The Wasm modules and the files of each component
+/// are pushed as layers, and the application manifest as the artifact config.
+pub async fn push_oci(app_file: impl AsRef<Path>, reference: &str) -> Result<()> {
+    let app_file = app_file
+        .as_ref()
+        .absolutize()
+        .context("Failed to resolve absolute path to manifest file")?;
+    let manifest = spin_loader::local::raw_manifest_from_file(&app_file).await?;
+    let local_schema::RawAppManifestAnyVersion::V1(manifest) = manifest;
+    let app_dir = app_file
+        .parent()
+        .ok_or_else(|| anyhow!("Manifest file has no parent directory"))?;
+
+    let client = OciClient::default();
+    let reference: Reference = reference.parse()?;
+
+    let mut layers = vec![];
+    let mut components = vec![];
+    for c in &manifest.components {
+        if c.wasm.volumes.is_some() {
+            bail!("This version of Spin can't publish components with volumes")
+        }
+        let source = match &c.source {
+            local_schema::RawModuleSource::FileReference(path) => app_dir.join(path),
+            local_schema::RawModuleSource::Bindle(_) => {
+                bail!("This version of Spin can't publish components whose sources are bindles")
+            }
+            local_schema::RawModuleSource::Url(_) => {
+                bail!("This version of Spin can't publish components whose sources are URLs")
+            }
+        };
+        let bytes = tokio::fs::read(&source)
+            .await
+            .with_context(|| anyhow!("Cannot read module source {}", source.display()))?;
+        let source = push_layer(
+            &client,
+            &reference,
+            WASM_LAYER_MEDIA_TYPE,
+            &bytes,
+            &mut layers,
+        )
+        .await?;
+
+        let files = match &c.wasm.files {
+            Some(mounts) => {
+                let exclude_files = c.wasm.exclude_files.clone().unwrap_or_default();
+                let files = spin_loader::local::assets::collect(mounts, &exclude_files, app_dir)
+                    .with_context(|| anyhow!("Failed to collect files for '{}'", c.id))?;
+                let archive = tokio::task::spawn_blocking(move || archive(files)).await??;
+                let digest = push_layer(
+                    &client,
+                    &reference,
+                    ARCHIVE_LAYER_MEDIA_TYPE,
+                    &archive,
+                    &mut layers,
+                )
+                .await?;
+                Some(digest)
+            }
+            None => None,
+        };
+
+        components.push(oci_schema::RawComponentManifest {
+            source,
+            id: c.id.clone(),
+            wasm: oci_schema::RawWasmConfig {
+                files,
+                allowed_http_hosts: c.wasm.allowed_http_hosts.clone(),
+                allowed_redis_addresses: c.wasm.allowed_redis_addresses.clone(),
+                key_value_stores: c.wasm.key_value_stores.clone(),
+                outbound_http: c.wasm.outbound_http.clone(),
+                environment: c.wasm.environment.clone(),
+            },
+            trigger: c.trigger.clone(),
+        });
+    }
+
+    let config = oci_schema::RawAppManifest {
+        name: manifest.info.name.clone(),
+        version: manifest.info.version.clone(),
+        description: manifest.info.description.clone(),
+        authors: manifest.info.authors.clone(),
+        namespace: manifest.info.namespace.clone(),
+        trigger: manifest.info.trigger.clone(),
+        key_value_stores: manifest.key_value_stores.clone(),
+        components,
+    };
+    let config = client
+        .push_blob(&reference, SPIN_CONFIG_MEDIA_TYPE, &toml::to_vec(&config)?)
+        .await?;
+
+    let manifest = OciManifest {
+        schema_version: 2,
+        media_type: Some(OCI_MANIFEST_MEDIA_TYPE.to_owned()),
+        config,
+        layers,
+        annotations: None,
+    };
+    client.push_manifest(&reference, &manifest).await
+}
+
+/// Pushes a layer, unless an identical layer is already part of the artifact,
+/// and returns its digest.
+async fn push_layer(
+    client: &OciClient,
+    reference: &Reference,
+    media_type: &str,
+    content: &[u8],
+    layers: &mut Vec<OciDescriptor>,
+) -> Result<String> {
+    let desc = OciDescriptor::for_content(media_type, content);
+    if !layers.contains(&desc) {
+        let desc = client.push_blob(reference, media_type, content).await?;
+        layers.push(desc);
+    }
+    Ok(desc.digest)
+}
+
+/// Builds a tar archive of the files, with fixed metadata so that the same
+/// files always produce the same layer digest.
+fn archive(mut files: Vec<FileMount>) -> Result<Vec<u8>> {
+    files.sort_by(|a, b| a.relative_dst.cmp(&b.relative_dst));
+    let mut builder = tar::Builder::new(vec![]);
+    for f in &files {
+        let content = std::fs::read(&f.src)
+            .with_context(|| anyhow!("Cannot read file {}", f.src.display()))?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        builder.append_data(&mut header, &f.relative_dst, content.as_slice())?;
+    }
+    Ok(builder.into_inner()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Body, Method, Request, Response, Server, StatusCode,
+    };
+    use spin_config::{ApplicationOrigin, ModuleSource};
+    use std::{
+        collections::HashMap,
+        convert::Infallible,
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+    };
+
+    const TEST_APP: &str = "../loader/tests/valid-with-files/spin.toml";
+
+    type Store = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+    /// Handles the subset of the OCI distribution API the client uses, storing
+    /// blobs and manifests in memory.
+    async fn registry(store: Store, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+        let path = req.uri().path().to_owned();
+        let query = req.uri().query().unwrap_or_default().to_owned();
+        let method = req.method().clone();
+        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+        let last = path.rsplit('/').next().unwrap_or_default().to_owned();
+
+        let mut store = store.lock().unwrap();
+        let res = match method {
+            Method::POST if path.ends_with("/blobs/uploads/") => Response::builder()
+                .status(StatusCode::ACCEPTED)
+                .header("Location", format!("{}upload", path))
+                .body(Body::empty()),
+            Method::PUT if path.contains("/blobs/uploads/") => {
+                let digest = query.trim_start_matches("digest=").replace("%3A", ":");
+                store.insert(digest, body.to_vec());
+                Response::builder()
+                    .status(StatusCode::CREATED)
+                    .body(Body::empty())
+            }
+            Method::PUT if path.contains("/manifests/") => {
+                store.insert(format!("manifest:{}", last), body.to_vec());
+                Response::builder()
+                    .status(StatusCode::CREATED)
+                    .body(Body::empty())
+            }
+            Method::GET | Method::HEAD => {
+                let key = match path.contains("/manifests/") {
+                    true => format!("manifest:{}", last),
+                    false => last,
+                };
+                match store.get(&key) {
+                    Some(content) if method == Method::GET => {
+                        Response::builder().body(Body::from(content.clone()))
+                    }
+                    Some(_) => Response::builder().body(Body::empty()),
+                    None => Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(Body::empty()),
+                }
+            }
+            _ => Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .body(Body::empty()),
+        };
+        Ok(res.unwrap())
+    }
+
+    fn serve_registry() -> (SocketAddr, Store) {
+        let store = Store::default();
+        let service_store = store.clone();
+        let make_svc = make_service_fn(move |_| {
+            let store = service_store.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |req| registry(store.clone(), req))) }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_svc);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        (addr, store)
+    }
+
+    #[tokio::test]
+    async fn test_push_and_pull_roundtrip() -> Result<()> {
+        let (addr, store) = serve_registry();
+        let reference = format!("localhost:{}/spin/fs:1.0.0", addr.port());
+
+        push_oci(TEST_APP, &reference).await?;
+        // The config, the module, the file archive and the manifest.
+        assert_eq!(store.lock().unwrap().len(), 4);
+
+        // Pushing again does not upload the blobs a second time.
+        push_oci(TEST_APP, &reference).await?;
+        assert_eq!(store.lock().unwrap().len(), 4);
+
+        let temp_dir = tempfile::tempdir()?;
+        let app = spin_loader::from_oci(&reference, temp_dir.path()).await?;
+        assert_eq!(app.info.name, "spin-local-source-test");
+        assert_eq!(app.info.version, "1.0.0");
+        assert_eq!(app.info.origin, ApplicationOrigin::Oci(reference));
+
+        let component = &app.components[0];
+        assert_eq!(component.id, "fs");
+        match &component.source {
+            ModuleSource::Buffer(bytes, _) => {
+                assert_eq!(
+                    bytes,
+                    &std::fs::read("../loader/tests/valid-with-files/spin-fs.wasm")?
+                )
+            }
+            ModuleSource::FileReference(_) => panic!("expected buffer module source"),
+        }
+
+        let mount = &component.wasm.mounts[0];
+        assert_eq!(mount.guest, "/");
+        assert!(mount.host.join("spin-fs.wasm").is_file());
+        assert!(mount.host.join("static").is_dir());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_components_with_volumes_are_not_published() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let app_file = temp_dir.path().join("spin.toml");
+        std::fs::write(
+            &app_file,
+            r#"
+            spin_version = "1"
+            name = "spin-volumes-test"
+            version = "1.0.0"
+            trigger = { type = "http", base = "/" }
+
+            [[component]]
+            id = "data"
+            source = "data.wasm"
+            volumes = [{ source = "data", destination = "/data", mode = "read-write" }]
+            [component.trigger]
+            route = "/..."
+            "#,
+        )?;
+
+        let err = push_oci(&app_file, "localhost:5000/spin/data:1.0.0")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("volumes"), "{:#}", err);
+
+        let err = crate::expand_manifest(&app_file, None, temp_dir.path().join("staging"))
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("volumes"), "{:#}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_is_deterministic() -> Result<()> {
+        let mounts = vec![local_schema::RawFileMount::Pattern("**/*".to_owned())];
+        let app_dir = Path::new("../loader/tests/valid-with-files").absolutize()?;
+        let files = spin_loader::local::assets::collect(&mounts, &[], app_dir)?;
+        assert_eq!(archive(files.clone())?, archive(files)?);
+        Ok(())
+    }
+}
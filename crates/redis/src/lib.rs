@@ -8,7 +8,8 @@ use async_trait::async_trait;
 use futures::StreamExt;
 use redis::Client;
 use spin_config::{
-    Application, ComponentMap, CoreComponent, RedisConfig, RedisTriggerConfiguration,
+    Application, ComponentMap, CoreComponent, OutboundHttpCassette, RedisConfig,
+    RedisTriggerConfiguration,
 };
 use spin_engine::{Builder, ExecutionContextConfiguration};
 use spin_redis::SpinRedisData;
@@ -34,7 +35,11 @@ pub struct RedisTrigger {
 
 impl RedisTrigger {
     /// Create a new Spin Redis trigger.
-    pub async fn new(app: Application<CoreComponent>, log_dir: Option<PathBuf>) -> Result<Self> {
+    pub async fn new(
+        app: Application<CoreComponent>,
+        log_dir: Option<PathBuf>,
+        outbound_http_cassette: Option<OutboundHttpCassette>,
+    ) -> Result<Self> {
         let trigger_config = app
             .info
             .trigger
@@ -58,6 +63,7 @@ impl RedisTrigger {
 
         let config = ExecutionContextConfiguration {
             log_dir,
+            outbound_http_cassette,
             ..app.into()
         };
         let engine = Arc::new(Builder::build_default(config).await?);
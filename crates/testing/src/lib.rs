@@ -10,7 +10,8 @@ use http::{Request, Response};
 use hyper::Body;
 use spin_config::{
     Application, ApplicationInformation, ApplicationOrigin, ApplicationTrigger, CoreComponent,
-    HttpConfig, ModuleSource, RedisConfig, RedisTriggerConfiguration, SpinVersion, TriggerConfig,
+    HttpConfig, ModuleSource, OutboundHttpCassette, OutboundHttpConfig, RedisConfig,
+    RedisTriggerConfiguration, SpinVersion, TriggerConfig, WasmConfig,
 };
 use spin_http_engine::HttpTrigger;
 
@@ -19,6 +20,9 @@ pub struct TestConfig {
     module_path: Option<PathBuf>,
     application_trigger: Option<ApplicationTrigger>,
     trigger_config: Option<TriggerConfig>,
+    allowed_http_hosts: Vec<String>,
+    outbound_http: OutboundHttpConfig,
+    outbound_http_cassette: Option<OutboundHttpCassette>,
 }
 
 impl TestConfig {
@@ -49,6 +53,21 @@ impl TestConfig {
         self
     }
 
+    pub fn allowed_http_hosts(&mut self, hosts: Vec<String>) -> &mut Self {
+        self.allowed_http_hosts = hosts;
+        self
+    }
+
+    pub fn outbound_http(&mut self, config: OutboundHttpConfig) -> &mut Self {
+        self.outbound_http = config;
+        self
+    }
+
+    pub fn outbound_http_cassette(&mut self, cassette: OutboundHttpCassette) -> &mut Self {
+        self.outbound_http_cassette = Some(cassette);
+        self
+    }
+
     pub fn build_application_information(&self) -> ApplicationInformation {
         ApplicationInformation {
             spin_version: SpinVersion::V1,
@@ -75,7 +94,11 @@ impl TestConfig {
         CoreComponent {
             source: ModuleSource::FileReference(module_path),
             id: "test-component".to_string(),
-            wasm: Default::default(),
+            wasm: WasmConfig {
+                allowed_http_hosts: self.allowed_http_hosts.clone(),
+                outbound_http: self.outbound_http.clone(),
+                ..Default::default()
+            },
         }
     }
 
@@ -91,13 +114,20 @@ impl TestConfig {
             )]
             .into_iter()
             .collect(),
+            key_value_stores: vec![],
         }
     }
 
     pub async fn build_http_trigger(&self) -> HttpTrigger {
-        HttpTrigger::new("".to_string(), self.build_configuration(), None, None)
-            .await
-            .expect("failed to build HttpTrigger")
+        HttpTrigger::new(
+            "".to_string(),
+            self.build_configuration(),
+            None,
+            None,
+            self.outbound_http_cassette.clone(),
+        )
+        .await
+        .expect("failed to build HttpTrigger")
     }
 
     pub async fn handle_http_request(&self, req: Request<Body>) -> anyhow::Result<Response<Body>> {
@@ -190,6 +190,14 @@ func toErr(code C.uint8_t, url string) error {
 		return fmt.Errorf("Error sending request to URL: %v", url)
 	case 4:
 		return fmt.Errorf("Runtime error")
+	case 5:
+		return fmt.Errorf("Timeout sending request to URL: %v", url)
+	case 6:
+		return fmt.Errorf("Response too large from URL: %v", url)
+	case 7:
+		return fmt.Errorf("Too many concurrent requests sending request to URL: %v", url)
+	case 8:
+		return fmt.Errorf("Too many redirects sending request to URL: %v", url)
 	default:
 		return nil
 	}
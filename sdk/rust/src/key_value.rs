@@ -0,0 +1,81 @@
+use std::fmt::{self, Display, Formatter};
+
+#[allow(missing_docs)]
+mod bindings {
+    wit_bindgen_rust::import!("../../wit/ephemeral/spin-kv.wit");
+}
+
+use bindings::spin_kv;
+
+/// Errors returned by key-value store operations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The store does not exist, or the component is not allowed to use it.
+    NoSuchStore,
+    /// The key does not exist in the store.
+    NoSuchKey,
+    /// The backend of the store failed to complete the operation.
+    Runtime(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSuchStore => f.write_str("no such key-value store"),
+            Self::NoSuchKey => f.write_str("no such key"),
+            Self::Runtime(e) => write!(f, "key-value store error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<spin_kv::Error> for Error {
+    fn from(e: spin_kv::Error) -> Self {
+        match e {
+            spin_kv::Error::NoSuchStore => Self::NoSuchStore,
+            spin_kv::Error::NoSuchKey => Self::NoSuchKey,
+            spin_kv::Error::Runtime(e) => Self::Runtime(e),
+        }
+    }
+}
+
+/// A named key-value store of the application.
+#[derive(Clone, Debug)]
+pub struct Store {
+    name: String,
+}
+
+impl Store {
+    /// Returns a handle to the store with the given name. Operations on the
+    /// handle fail with `Error::NoSuchStore` if the component is not allowed
+    /// to use the store.
+    pub fn open(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+
+    /// Returns the value stored for the key.
+    pub fn get(&self, key: &str) -> Result<Vec<u8>, Error> {
+        Ok(spin_kv::get(&self.name, key)?)
+    }
+
+    /// Stores the value for the key, replacing any previous value.
+    pub fn set(&self, key: &str, value: impl AsRef<[u8]>) -> Result<(), Error> {
+        Ok(spin_kv::set(&self.name, key, value.as_ref())?)
+    }
+
+    /// Removes the key. Removing a key that does not exist is not an error.
+    pub fn delete(&self, key: &str) -> Result<(), Error> {
+        Ok(spin_kv::delete(&self.name, key)?)
+    }
+
+    /// Returns whether the key exists.
+    pub fn exists(&self, key: &str) -> Result<bool, Error> {
+        Ok(spin_kv::exists(&self.name, key)?)
+    }
+
+    /// Returns all the keys of the store.
+    pub fn list_keys(&self) -> Result<Vec<String>, Error> {
+        Ok(spin_kv::list_keys(&self.name)?)
+    }
+}
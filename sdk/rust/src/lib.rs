@@ -8,6 +8,14 @@ pub use spin_macro::*;
 /// Exports the experimental outbound HTTP crate.
 pub use wasi_experimental_http as outbound_http;
 
+/// Access to the key-value stores declared by the application.
+/// A component can only use the stores listed in its `key_value_stores`.
+pub mod key_value;
+
+/// Sending commands to Redis servers. A component can only connect to the
+/// servers listed in its `allowed_redis_addresses`.
+pub mod redis;
+
 /// Helpers for building Spin HTTP components.
 /// These are convenience helpers, and the types in this module are
 /// based on the [`http`](https://crates.io/crates) crate.
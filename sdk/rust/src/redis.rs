@@ -0,0 +1,47 @@
+use std::fmt::{self, Display, Formatter};
+
+#[allow(missing_docs)]
+mod bindings {
+    wit_bindgen_rust::import!("../../wit/ephemeral/outbound-redis.wit");
+}
+
+use bindings::outbound_redis;
+
+/// The error returned when a Redis operation fails, for example because the
+/// component is not allowed to connect to the server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Error;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("outbound Redis operation failed")
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<outbound_redis::Error> for Error {
+    fn from(_: outbound_redis::Error) -> Self {
+        Self
+    }
+}
+
+/// Publishes a message to a channel of the Redis server at the given address.
+pub fn publish(address: &str, channel: &str, payload: impl AsRef<[u8]>) -> Result<(), Error> {
+    Ok(outbound_redis::publish(address, channel, payload.as_ref())?)
+}
+
+/// Gets the value of a key. The value is empty if the key does not exist.
+pub fn get(address: &str, key: &str) -> Result<Vec<u8>, Error> {
+    Ok(outbound_redis::get(address, key)?)
+}
+
+/// Sets the value of a key, replacing any previous value.
+pub fn set(address: &str, key: &str, value: impl AsRef<[u8]>) -> Result<(), Error> {
+    Ok(outbound_redis::set(address, key, value.as_ref())?)
+}
+
+/// Increments the number stored at a key by one, and returns the new value.
+pub fn incr(address: &str, key: &str) -> Result<i64, Error> {
+    Ok(outbound_redis::incr(address, key)?)
+}
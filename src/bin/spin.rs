@@ -1,6 +1,8 @@
 use anyhow::Error;
 use spin_cli::commands::{
-    bindle::BindleCommands, new::NewCommand, templates::TemplateCommands, up::UpCommand,
+    bindle::BindleCommands, build::BuildCommand, cache::CacheCommands, inspect::InspectCommand,
+    new::NewCommand, oci::OciCommands, schema::SchemaCommand, templates::TemplateCommands,
+    up::UpCommand, validate::ValidateCommand,
 };
 use structopt::{clap::AppSettings, StructOpt};
 
@@ -28,6 +30,12 @@ enum SpinApp {
     New(NewCommand),
     Up(UpCommand),
     Bindle(BindleCommands),
+    Build(BuildCommand),
+    Validate(ValidateCommand),
+    Schema(SchemaCommand),
+    Inspect(InspectCommand),
+    Oci(OciCommands),
+    Cache(CacheCommands),
 }
 
 impl SpinApp {
@@ -38,6 +46,12 @@ impl SpinApp {
             SpinApp::Up(cmd) => cmd.run().await,
             SpinApp::New(cmd) => cmd.run().await,
             SpinApp::Bindle(cmd) => cmd.run().await,
+            SpinApp::Build(cmd) => cmd.run().await,
+            SpinApp::Validate(cmd) => cmd.run().await,
+            SpinApp::Schema(cmd) => cmd.run().await,
+            SpinApp::Inspect(cmd) => cmd.run().await,
+            SpinApp::Oci(cmd) => cmd.run().await,
+            SpinApp::Cache(cmd) => cmd.run().await,
         }
     }
 }
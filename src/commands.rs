@@ -2,9 +2,21 @@
 
 /// Command for creating bindles.
 pub mod bindle;
+/// Command for building the components of an application.
+pub mod build;
+/// Commands for managing the local parcel cache.
+pub mod cache;
+/// Command for inspecting the modules of an application.
+pub mod inspect;
 /// Command for creating a new application.
 pub mod new;
+/// Commands for publishing applications to OCI registries.
+pub mod oci;
+/// Command for printing the manifest JSON schema.
+pub mod schema;
 /// Commands for working with templates.
 pub mod templates;
 /// Commands for starting the runtime.
 pub mod up;
+/// Command for checking an application manifest.
+pub mod validate;
@@ -1,16 +1,27 @@
 use anyhow::{Context, Result};
 use bindle::client::Client as BindleClient;
-use bindle::client::ClientBuilder as BindleClientBuilder;
 use semver::{BuildMetadata, Error};
-use spin_loader::bindle::BindleTokenManager;
+use spin_loader::bindle::{
+    BindleAuth, BindleConnectionInfo, BindleTokenManager, BINDLE_PASSWORD_ENV, BINDLE_TOKEN_ENV,
+    BINDLE_USERNAME_ENV,
+};
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
 const APP_CONFIG_FILE_OPT: &str = "APP_CONFIG_FILE";
 const BINDLE_SERVER_URL_OPT: &str = "BINDLE_SERVER_URL";
 const BINDLE_URL_ENV: &str = "BINDLE_URL";
+const BINDLE_USERNAME_OPT: &str = "BINDLE_USERNAME";
+const BINDLE_PASSWORD_OPT: &str = "BINDLE_PASSWORD";
+const BINDLE_TOKEN_OPT: &str = "BINDLE_TOKEN";
+const BINDLE_ID_OPT: &str = "BINDLE_ID";
 const BUILDINFO_OPT: &str = "BUILDINFO";
+const EXPORT_DIR_OPT: &str = "EXPORT_DIR";
 const INSECURE_OPT: &str = "INSECURE";
+const KEY_LABEL_OPT: &str = "KEY_LABEL";
+const SECRET_KEYS_FILE_OPT: &str = "SECRET_KEYS_FILE";
+const SIGN_WITH_OPT: &str = "SIGN_WITH";
+const TRUSTED_KEYS_FILE_OPT: &str = "TRUSTED_KEYS_FILE";
 const STAGING_DIR_OPT: &str = "STAGING_DIR";
 
 /// Commands for publishing applications as bindles.
@@ -21,6 +32,12 @@ pub enum BindleCommands {
 
     /// Publish an application as a bindle.
     Push(Push),
+
+    /// Fetch a published bindle into a standalone bindle directory.
+    Pull(Pull),
+
+    /// Create a keypair for signing bindles.
+    Keygen(Keygen),
 }
 
 impl BindleCommands {
@@ -28,6 +45,8 @@ impl BindleCommands {
         match self {
             Self::Prepare(cmd) => cmd.run().await,
             Self::Push(cmd) => cmd.run().await,
+            Self::Pull(cmd) => cmd.run().await,
+            Self::Keygen(cmd) => cmd.run().await,
         }
     }
 }
@@ -62,6 +81,13 @@ pub struct Prepare {
         short = "-d",
     )]
     pub staging_dir: PathBuf,
+
+    /// Path to a secret keys file to sign the invoice with.
+    #[structopt(
+        name = SIGN_WITH_OPT,
+        long = "sign-with",
+    )]
+    pub sign_with: Option<PathBuf>,
 }
 
 /// Publish an application as a bindle.
@@ -92,6 +118,13 @@ pub struct Push {
     )]
     pub staging_dir: Option<PathBuf>,
 
+    /// Path to a secret keys file to sign the invoice with.
+    #[structopt(
+        name = SIGN_WITH_OPT,
+        long = "sign-with",
+    )]
+    pub sign_with: Option<PathBuf>,
+
     /// URL of bindle server
     #[structopt(
         name = BINDLE_SERVER_URL_OPT,
@@ -100,6 +133,32 @@ pub struct Push {
     )]
     pub bindle_server_url: String,
 
+    /// Username for HTTP basic authentication with the bindle server.
+    #[structopt(
+        name = BINDLE_USERNAME_OPT,
+        long = "bindle-username",
+        env = BINDLE_USERNAME_ENV,
+    )]
+    pub bindle_username: Option<String>,
+
+    /// Password for HTTP basic authentication with the bindle server.
+    #[structopt(
+        name = BINDLE_PASSWORD_OPT,
+        long = "bindle-password",
+        env = BINDLE_PASSWORD_ENV,
+        hide_env_values = true,
+    )]
+    pub bindle_password: Option<String>,
+
+    /// Bearer token for authentication with the bindle server.
+    #[structopt(
+        name = BINDLE_TOKEN_OPT,
+        long = "bindle-token",
+        env = BINDLE_TOKEN_ENV,
+        hide_env_values = true,
+    )]
+    pub bindle_token: Option<String>,
+
     /// Ignore server certificate errors
     #[structopt(
         name = INSECURE_OPT,
@@ -110,6 +169,94 @@ pub struct Push {
     pub insecure: bool,
 }
 
+/// Fetch a published bindle into a standalone bindle directory.
+#[derive(StructOpt, Debug)]
+pub struct Pull {
+    /// ID of the bindle to fetch
+    #[structopt(name = BINDLE_ID_OPT)]
+    pub bindle_id: bindle::Id,
+
+    /// Path to write the standalone bindle to.
+    #[structopt(
+        name = STAGING_DIR_OPT,
+        long = "staging-dir",
+        short = "-d",
+    )]
+    pub staging_dir: PathBuf,
+
+    /// Path to reconstitute a runnable spin.toml, with its modules and files, into.
+    #[structopt(
+        name = EXPORT_DIR_OPT,
+        long = "export-dir",
+    )]
+    pub export_dir: Option<PathBuf>,
+
+    /// URL of bindle server
+    #[structopt(
+        name = BINDLE_SERVER_URL_OPT,
+        long = "bindle-server",
+        env = BINDLE_URL_ENV,
+    )]
+    pub bindle_server_url: String,
+
+    /// Username for HTTP basic authentication with the bindle server.
+    #[structopt(
+        name = BINDLE_USERNAME_OPT,
+        long = "bindle-username",
+        env = BINDLE_USERNAME_ENV,
+    )]
+    pub bindle_username: Option<String>,
+
+    /// Password for HTTP basic authentication with the bindle server.
+    #[structopt(
+        name = BINDLE_PASSWORD_OPT,
+        long = "bindle-password",
+        env = BINDLE_PASSWORD_ENV,
+        hide_env_values = true,
+    )]
+    pub bindle_password: Option<String>,
+
+    /// Bearer token for authentication with the bindle server.
+    #[structopt(
+        name = BINDLE_TOKEN_OPT,
+        long = "bindle-token",
+        env = BINDLE_TOKEN_ENV,
+        hide_env_values = true,
+    )]
+    pub bindle_token: Option<String>,
+
+    /// Ignore server certificate errors
+    #[structopt(
+        name = INSECURE_OPT,
+        short = "k",
+        long = "insecure",
+        takes_value = false,
+    )]
+    pub insecure: bool,
+}
+
+/// Create a keypair for signing bindles.
+#[derive(StructOpt, Debug)]
+pub struct Keygen {
+    /// Label identifying the key, such as a name and email address.
+    #[structopt(name = KEY_LABEL_OPT, long = "label")]
+    pub label: String,
+
+    /// Path to the secret keys file to add the key to.
+    #[structopt(
+        name = SECRET_KEYS_FILE_OPT,
+        long = "secret-file",
+    )]
+    pub secret_file: PathBuf,
+
+    /// Path to a trusted keys file to add the public key to.
+    #[structopt(
+        name = TRUSTED_KEYS_FILE_OPT,
+        long = "trusted-keys",
+    )]
+    pub trusted_keys: Option<PathBuf>,
+}
+
 impl Prepare {
     pub async fn run(self) -> Result<()> {
         let source_dir = app_dir(&self.app)?;
@@ -121,6 +268,7 @@ impl Prepare {
                 .with_context(|| {
                     format!("Failed to expand '{}' to a bindle", self.app.display())
                 })?;
+        let invoice = sign_if_requested(invoice, &self.sign_with).await?;
 
         let bindle_id = &invoice.bindle.id;
 
@@ -158,6 +306,7 @@ impl Push {
                 .with_context(|| {
                     format!("Failed to expand '{}' to a bindle", self.app.display())
                 })?;
+        let invoice = sign_if_requested(invoice, &self.sign_with).await?;
 
         let bindle_id = &invoice.bindle.id;
 
@@ -174,19 +323,69 @@ impl Push {
     }
 
     fn create_bindle_client(&self) -> Result<BindleClient<BindleTokenManager>> {
-        BindleClientBuilder::default()
-            .danger_accept_invalid_certs(self.insecure)
-            .build(
-                &self.bindle_server_url,
-                // TODO: pick up auth options from the command line
-                BindleTokenManager::NoToken(bindle::client::tokens::NoToken),
-            )
+        let auth = BindleAuth {
+            username: self.bindle_username.clone(),
+            password: self.bindle_password.clone(),
+            token: self.bindle_token.clone(),
+        };
+        BindleConnectionInfo::new(&self.bindle_server_url, self.insecure, auth)?.client()
+    }
+}
+
+impl Pull {
+    pub async fn run(self) -> Result<()> {
+        let auth = BindleAuth {
+            username: self.bindle_username.clone(),
+            password: self.bindle_password.clone(),
+            token: self.bindle_token.clone(),
+        };
+        let client =
+            BindleConnectionInfo::new(&self.bindle_server_url, self.insecure, auth)?.client()?;
+
+        spin_publish::pull_all(&client, &self.bindle_id, &self.staging_dir)
+            .await
             .with_context(|| {
                 format!(
-                    "Failed to create client for bindle server '{}'",
-                    self.bindle_server_url
+                    "Failed to pull bindle '{}' from server at '{}'",
+                    self.bindle_id, self.bindle_server_url
                 )
-            })
+            })?;
+        println!("pulled: {}", self.bindle_id);
+
+        if let Some(export_dir) = &self.export_dir {
+            let app_file = spin_publish::export_app(&self.staging_dir, &self.bindle_id, export_dir)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to export bindle '{}' to {}",
+                        self.bindle_id,
+                        export_dir.display()
+                    )
+                })?;
+            println!("app:    {}", app_file.display());
+        }
+        Ok(())
+    }
+}
+
+impl Keygen {
+    pub async fn run(self) -> Result<()> {
+        spin_publish::create_key(&self.label, &self.secret_file, self.trusted_keys.as_deref())
+            .await?;
+        println!("key:    {}", self.label);
+        Ok(())
+    }
+}
+
+async fn sign_if_requested(
+    invoice: bindle::Invoice,
+    secret_keys_file: &Option<PathBuf>,
+) -> Result<bindle::Invoice> {
+    match secret_keys_file {
+        Some(path) => spin_publish::sign_invoice(invoice, path)
+            .await
+            .with_context(|| format!("Failed to sign with keys from '{}'", path.display())),
+        None => Ok(invoice),
     }
 }
 
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use std::{ffi::OsString, path::PathBuf};
+use structopt::StructOpt;
+
+use super::up::UpCommand;
+
+const APP_CONFIG_FILE_OPT: &str = "APP_CONFIG_FILE";
+const BUILD_UP_OPT: &str = "UP";
+const DEFAULT_MANIFEST_FILE: &str = "spin.toml";
+
+/// Run the build command for each component.
+#[derive(StructOpt, Debug)]
+#[structopt(about = "Build the Spin application")]
+pub struct BuildCommand {
+    /// Path to spin.toml.
+    #[structopt(
+        name = APP_CONFIG_FILE_OPT,
+        short = "f",
+        long = "file",
+    )]
+    pub app: Option<PathBuf>,
+
+    /// Run the build commands of all components concurrently.
+    #[structopt(long = "parallel")]
+    pub parallel: bool,
+
+    /// Run the application after building it.
+    #[structopt(name = BUILD_UP_OPT, short = "u", long = "up")]
+    pub up: bool,
+
+    /// Arguments to pass to `spin up` after a successful build.
+    #[structopt(requires = BUILD_UP_OPT)]
+    pub up_args: Vec<OsString>,
+}
+
+impl BuildCommand {
+    pub async fn run(self) -> Result<()> {
+        let manifest_file = self
+            .app
+            .as_deref()
+            .unwrap_or_else(|| DEFAULT_MANIFEST_FILE.as_ref());
+
+        let built = spin_build::build(manifest_file, self.parallel).await?;
+        if built.is_empty() {
+            println!("No build command found!");
+        } else {
+            println!("Successfully ran the build command for the Spin components.");
+        }
+
+        if self.up {
+            let cmd = UpCommand::from_iter_safe(
+                [
+                    OsString::from("up"),
+                    OsString::from("--file"),
+                    manifest_file.as_os_str().to_owned(),
+                ]
+                .into_iter()
+                .chain(self.up_args),
+            )?;
+            cmd.run().await
+        } else {
+            Ok(())
+        }
+    }
+}
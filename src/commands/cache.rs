@@ -0,0 +1,142 @@
+use anyhow::{anyhow, bail, Context, Result};
+use spin_loader::cache::{self, CacheEntry};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+const CACHE_DIR_OPT: &str = "CACHE_DIR";
+const MAX_SIZE_OPT: &str = "MAX_SIZE";
+
+/// Commands for managing the local parcel cache.
+#[derive(StructOpt, Debug)]
+pub enum CacheCommands {
+    /// Show the location, number of parcels and size of the cache.
+    Info(Info),
+
+    /// Evict the least recently used parcels until the cache fits in a size.
+    Prune(Prune),
+
+    /// Remove all parcels from the cache.
+    Clear(Clear),
+}
+
+impl CacheCommands {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            Self::Info(cmd) => cmd.run().await,
+            Self::Prune(cmd) => cmd.run().await,
+            Self::Clear(cmd) => cmd.run().await,
+        }
+    }
+}
+
+/// Show the location, number of parcels and size of the cache.
+#[derive(StructOpt, Debug)]
+pub struct Info {
+    /// Directory of the parcel cache. Defaults to `spin/parcels` in the user cache directory.
+    #[structopt(name = CACHE_DIR_OPT, long = "cache-dir")]
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl Info {
+    pub async fn run(self) -> Result<()> {
+        let dir = cache_dir(self.cache_dir)?;
+        let entries = cache::entries(&dir).await?;
+        println!("Location: {}", dir.display());
+        println!("Parcels: {}", entries.len());
+        println!("Size: {}", format_size(total_size(&entries)));
+        println!("Limit: {}", format_size(cache::max_cache_size()?));
+        Ok(())
+    }
+}
+
+/// Evict the least recently used parcels until the cache fits in a size.
+#[derive(StructOpt, Debug)]
+pub struct Prune {
+    /// Directory of the parcel cache. Defaults to `spin/parcels` in the user cache directory.
+    #[structopt(name = CACHE_DIR_OPT, long = "cache-dir")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Maximum size of the cache, in bytes or with a K, M or G suffix.
+    /// Defaults to the configured cache size limit.
+    #[structopt(name = MAX_SIZE_OPT, long = "max-size", parse(try_from_str = parse_size))]
+    pub max_size: Option<u64>,
+}
+
+impl Prune {
+    pub async fn run(self) -> Result<()> {
+        let dir = cache_dir(self.cache_dir)?;
+        let max_size = match self.max_size {
+            Some(size) => size,
+            None => cache::max_cache_size()?,
+        };
+        let removed = cache::prune(&dir, max_size).await?;
+        println!(
+            "Removed {} parcel(s), {}",
+            removed.len(),
+            format_size(total_size(&removed))
+        );
+        Ok(())
+    }
+}
+
+/// Remove all parcels from the cache.
+#[derive(StructOpt, Debug)]
+pub struct Clear {
+    /// Directory of the parcel cache. Defaults to `spin/parcels` in the user cache directory.
+    #[structopt(name = CACHE_DIR_OPT, long = "cache-dir")]
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl Clear {
+    pub async fn run(self) -> Result<()> {
+        let dir = cache_dir(self.cache_dir)?;
+        let removed = cache::clear(&dir).await?;
+        println!(
+            "Removed {} parcel(s), {}",
+            removed.len(),
+            format_size(total_size(&removed))
+        );
+        Ok(())
+    }
+}
+
+fn cache_dir(dir: Option<PathBuf>) -> Result<PathBuf> {
+    dir.or_else(cache::default_cache_dir)
+        .ok_or_else(|| anyhow!("Cannot find the user cache directory: specify --cache-dir"))
+}
+
+fn total_size(entries: &[CacheEntry]) -> u64 {
+    entries.iter().map(|e| e.size).sum()
+}
+
+fn parse_size(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let (number, multiplier) = match text.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&text[..i], 1024),
+        Some((i, 'M' | 'm')) => (&text[..i], 1024 * 1024),
+        Some((i, 'G' | 'g')) => (&text[..i], 1024 * 1024 * 1024),
+        Some(_) => (text, 1),
+        None => bail!("Size cannot be empty"),
+    };
+    let number: u64 = number
+        .trim()
+        .parse()
+        .with_context(|| anyhow!("Invalid size '{}'", text))?;
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| anyhow!("Size '{}' is too large", text))
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["bytes", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} bytes", bytes),
+        _ => format!("{:.1} {}", size, UNITS[unit]),
+    }
+}
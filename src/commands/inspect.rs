@@ -0,0 +1,133 @@
+use anyhow::{bail, Result};
+use spin_config::{HttpExecutor, RedisExecutor, TriggerConfig};
+use spin_loader::bindle::{BindleAuth, BindleConnectionInfo, SignaturePolicy};
+use spin_loader::inspect::{inspect_source, SPIN_HTTP_EXPORT, SPIN_REDIS_EXPORT};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+const APP_CONFIG_FILE_OPT: &str = "APP_CONFIG_FILE";
+const BINDLE_ID_OPT: &str = "BINDLE_ID";
+const BINDLE_SERVER_URL_OPT: &str = "BINDLE_SERVER_URL";
+const BINDLE_URL_ENV: &str = "BINDLE_URL";
+const DEFAULT_MANIFEST_FILE: &str = "spin.toml";
+
+/// Report the imports and exports of the modules of an application.
+#[derive(StructOpt, Debug)]
+#[structopt(about = "Inspect the WebAssembly modules of the Spin application")]
+pub struct InspectCommand {
+    /// Path to spin.toml.
+    #[structopt(
+        name = APP_CONFIG_FILE_OPT,
+        short = "f",
+        long = "file",
+        conflicts_with = BINDLE_ID_OPT,
+    )]
+    pub app: Option<PathBuf>,
+    /// ID of application bindle.
+    #[structopt(
+        name = BINDLE_ID_OPT,
+        short = "b",
+        long = "bindle",
+        conflicts_with = APP_CONFIG_FILE_OPT,
+        requires = BINDLE_SERVER_URL_OPT,
+    )]
+    pub bindle: Option<String>,
+    /// URL of bindle server.
+    #[structopt(
+        name = BINDLE_SERVER_URL_OPT,
+        long = "server",
+        env = BINDLE_URL_ENV,
+    )]
+    pub server: Option<String>,
+}
+
+impl InspectCommand {
+    pub async fn run(self) -> Result<()> {
+        let working_dir = tempfile::tempdir()?;
+
+        let app = match (&self.app, &self.bindle) {
+            (app, None) => {
+                let manifest_file = app
+                    .as_deref()
+                    .unwrap_or_else(|| DEFAULT_MANIFEST_FILE.as_ref());
+                spin_loader::from_file(manifest_file, working_dir.path()).await?
+            }
+            (None, Some(bindle)) => match &self.server {
+                Some(server) => {
+                    let connection =
+                        BindleConnectionInfo::new(server, false, BindleAuth::from_env())?;
+                    let policy = SignaturePolicy::new(false, None).await?;
+                    spin_loader::from_bindle(bindle, &connection, &policy, working_dir.path())
+                        .await?
+                }
+                _ => bail!("Loading from a bindle requires a Bindle server URL"),
+            },
+            (Some(_), Some(_)) => bail!("Specify only one of app file or bindle ID"),
+        };
+
+        for c in &app.components {
+            let info = inspect_source(&c.source).await?;
+
+            println!("Component: {}", c.id);
+            println!("  Source: {:?}", c.source);
+
+            let interfaces = info
+                .interfaces()
+                .iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>();
+            println!("  Interfaces: {}", list_or_none(&interfaces));
+
+            if let Some(export) = app.component_triggers.get(c).map(required_export) {
+                let status = if info.exports(&export) {
+                    "found"
+                } else {
+                    "MISSING"
+                };
+                println!("  Required export: {} ({})", export, status);
+            }
+
+            println!("  Exports: {}", list_or_none(&info.exports));
+
+            let imports = info
+                .imports
+                .iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>();
+            println!("  Imports: {}", list_or_none(&imports));
+
+            let unsatisfied = info
+                .unsatisfied_imports()
+                .iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>();
+            println!("  Unsatisfied imports: {}", list_or_none(&unsatisfied));
+            println!();
+        }
+
+        drop(working_dir);
+
+        Ok(())
+    }
+}
+
+/// Returns the export the executor of the component calls.
+fn required_export(trigger: &TriggerConfig) -> String {
+    match trigger {
+        TriggerConfig::Http(http) => match http.executor.clone().unwrap_or_default() {
+            HttpExecutor::Spin => SPIN_HTTP_EXPORT.to_owned(),
+            HttpExecutor::Wagi(wagi) => wagi.entrypoint,
+        },
+        TriggerConfig::Redis(redis) => match redis.executor.clone().unwrap_or_default() {
+            RedisExecutor::Spin => SPIN_REDIS_EXPORT.to_owned(),
+        },
+    }
+}
+
+fn list_or_none(items: &[String]) -> String {
+    if items.is_empty() {
+        "(none)".to_owned()
+    } else {
+        items.join(", ")
+    }
+}
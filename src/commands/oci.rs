@@ -0,0 +1,46 @@
+use anyhow::Result;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+const APP_CONFIG_FILE_OPT: &str = "APP_CONFIG_FILE";
+const OCI_REFERENCE_OPT: &str = "OCI_REFERENCE";
+
+/// Commands for publishing applications to OCI registries.
+#[derive(StructOpt, Debug)]
+pub enum OciCommands {
+    /// Push an application to an OCI registry.
+    Push(Push),
+}
+
+impl OciCommands {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            Self::Push(cmd) => cmd.run().await,
+        }
+    }
+}
+
+/// Push an application to an OCI registry.
+#[derive(StructOpt, Debug)]
+pub struct Push {
+    /// Path to spin.toml
+    #[structopt(
+        name = APP_CONFIG_FILE_OPT,
+        short = "f",
+        long = "file",
+    )]
+    pub app: PathBuf,
+
+    /// Reference to push the application to, such as `localhost:5000/spin/app:1.0.0`.
+    /// Only registries that do not require authentication are supported.
+    #[structopt(name = OCI_REFERENCE_OPT)]
+    pub reference: String,
+}
+
+impl Push {
+    pub async fn run(self) -> Result<()> {
+        spin_publish::push_oci(&self.app, &self.reference).await?;
+        println!("pushed: {}", self.reference);
+        Ok(())
+    }
+}
@@ -0,0 +1,30 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Print the JSON schema for spin.toml application manifests.
+#[derive(StructOpt, Debug)]
+#[structopt(about = "Print the JSON schema for the Spin application manifest")]
+pub struct SchemaCommand {
+    /// File to write the schema to. If omitted, the schema is printed to stdout.
+    #[structopt(short = "o", long = "output")]
+    pub output: Option<PathBuf>,
+}
+
+impl SchemaCommand {
+    pub async fn run(self) -> Result<()> {
+        let schema = spin_loader::local::config::manifest_schema();
+        let text =
+            serde_json::to_string_pretty(&schema).context("Failed to serialize manifest schema")?;
+
+        match &self.output {
+            Some(path) => tokio::fs::write(path, text)
+                .await
+                .with_context(|| format!("Failed to write schema to {}", path.display())),
+            None => {
+                println!("{}", text);
+                Ok(())
+            }
+        }
+    }
+}
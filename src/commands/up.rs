@@ -1,6 +1,11 @@
 use anyhow::{bail, Result};
-use spin_config::{Application, ApplicationTrigger, CoreComponent};
+use spin_config::{Application, ApplicationTrigger, CoreComponent, OutboundHttpCassette};
 use spin_http_engine::{HttpTrigger, TlsConfig};
+use spin_loader::bindle::{
+    BindleAuth, BindleConnectionInfo, SignaturePolicy, BINDLE_PASSWORD_ENV, BINDLE_TOKEN_ENV,
+    BINDLE_USERNAME_ENV,
+};
+use spin_loader::FilesMountStrategy;
 use spin_redis_engine::RedisTrigger;
 use std::path::{Path, PathBuf};
 use structopt::{clap::AppSettings, StructOpt};
@@ -9,10 +14,21 @@ use tempfile::TempDir;
 const DEFAULT_MANIFEST_FILE: &str = "spin.toml";
 
 const APP_CONFIG_FILE_OPT: &str = "APP_CONFIG_FILE";
+const COPY_FILES_OPT: &str = "COPY_FILES";
 const BINDLE_ID_OPT: &str = "BINDLE_ID";
+const BINDLE_DIR_OPT: &str = "BINDLE_DIR";
+const REQUIRE_SIGNATURE_OPT: &str = "REQUIRE_SIGNATURE";
+const TRUSTED_KEYS_FILE_OPT: &str = "TRUSTED_KEYS_FILE";
 const BINDLE_SERVER_URL_OPT: &str = "BINDLE_SERVER_URL";
 const BINDLE_URL_ENV: &str = "BINDLE_URL";
+const BINDLE_USERNAME_OPT: &str = "BINDLE_USERNAME";
+const BINDLE_PASSWORD_OPT: &str = "BINDLE_PASSWORD";
+const BINDLE_TOKEN_OPT: &str = "BINDLE_TOKEN";
+const OCI_REFERENCE_OPT: &str = "OCI_REFERENCE";
 const APP_LOG_DIR: &str = "APP_LOG_DIR";
+const OUTBOUND_HTTP_RECORD_OPT: &str = "OUTBOUND_HTTP_RECORD";
+const OUTBOUND_HTTP_REPLAY_OPT: &str = "OUTBOUND_HTTP_REPLAY";
+const OUTBOUND_HTTP_MATCH_HEADER_OPT: &str = "OUTBOUND_HTTP_MATCH_HEADER";
 
 const TLS_CERT_FILE_OPT: &str = "TLS_CERT_FILE";
 const TLS_KEY_FILE_OPT: &str = "TLS_KEY_FILE";
@@ -36,18 +52,52 @@ pub struct UpCommand {
         name = APP_CONFIG_FILE_OPT,
         short = "f",
         long = "file",
-        conflicts_with = BINDLE_ID_OPT,
+        conflicts_with_all = &[BINDLE_ID_OPT, OCI_REFERENCE_OPT],
     )]
     pub app: Option<PathBuf>,
+    /// Copy the files of components into the working directory, instead of
+    /// mounting them read-only from the application directory. This isolates the
+    /// running application from changes to the source tree.
+    #[structopt(
+        name = COPY_FILES_OPT,
+        long = "copy-files",
+        conflicts_with_all = &[BINDLE_ID_OPT, OCI_REFERENCE_OPT],
+        takes_value = false,
+    )]
+    pub copy_files: bool,
     /// ID of application bindle.
     #[structopt(
         name = BINDLE_ID_OPT,
         short = "b",
         long = "bindle",
-        conflicts_with = APP_CONFIG_FILE_OPT,
-        requires = BINDLE_SERVER_URL_OPT,
+        conflicts_with_all = &[APP_CONFIG_FILE_OPT, OCI_REFERENCE_OPT],
     )]
     pub bindle: Option<String>,
+    /// Standalone bindle directory (as written by `spin bindle prepare`) to load
+    /// the bindle from, instead of a bindle server.
+    #[structopt(
+        name = BINDLE_DIR_OPT,
+        long = "bindle-dir",
+        requires = BINDLE_ID_OPT,
+    )]
+    pub bindle_dir: Option<PathBuf>,
+    /// Refuse to run bindles, including the bindles component modules are
+    /// fetched from, that are not signed by a trusted key.
+    #[structopt(
+        name = REQUIRE_SIGNATURE_OPT,
+        long = "require-signature",
+        conflicts_with = OCI_REFERENCE_OPT,
+        takes_value = false,
+    )]
+    pub require_signature: bool,
+    /// Path to the keyring of keys trusted to sign bindles (defaults to
+    /// `spin/trusted-keys.toml` in the user configuration directory).
+    #[structopt(
+        name = TRUSTED_KEYS_FILE_OPT,
+        long = "trusted-keys",
+        conflicts_with = OCI_REFERENCE_OPT,
+    )]
+    pub trusted_keys: Option<PathBuf>,
     /// URL of bindle server.
     #[structopt(
         name = BINDLE_SERVER_URL_OPT,
@@ -55,6 +105,37 @@ pub struct UpCommand {
         env = BINDLE_URL_ENV,
     )]
     pub server: Option<String>,
+    /// Username for HTTP basic authentication with the bindle server.
+    #[structopt(
+        name = BINDLE_USERNAME_OPT,
+        long = "bindle-username",
+        env = BINDLE_USERNAME_ENV,
+    )]
+    pub bindle_username: Option<String>,
+    /// Password for HTTP basic authentication with the bindle server.
+    #[structopt(
+        name = BINDLE_PASSWORD_OPT,
+        long = "bindle-password",
+        env = BINDLE_PASSWORD_ENV,
+        hide_env_values = true,
+    )]
+    pub bindle_password: Option<String>,
+    /// Bearer token for authentication with the bindle server.
+    #[structopt(
+        name = BINDLE_TOKEN_OPT,
+        long = "bindle-token",
+        env = BINDLE_TOKEN_ENV,
+        hide_env_values = true,
+    )]
+    pub bindle_token: Option<String>,
+    /// Reference of the application in an OCI registry, such as `localhost:5000/spin/app:1.0.0`.
+    /// Only public applications can be run: registry credentials are not supported.
+    #[structopt(
+        name = OCI_REFERENCE_OPT,
+        long = "oci",
+        conflicts_with_all = &[APP_CONFIG_FILE_OPT, BINDLE_ID_OPT],
+    )]
+    pub oci: Option<String>,
     /// Temporary directory for the static assets of the components.
     #[structopt(long = "temp")]
     pub tmp: Option<PathBuf>,
@@ -86,6 +167,31 @@ pub struct UpCommand {
         long = "log-dir",
         )]
     pub log: Option<PathBuf>,
+    /// Record the outbound HTTP requests of the components and their responses
+    /// to a cassette file, replacing any previous recording.
+    #[structopt(
+        name = OUTBOUND_HTTP_RECORD_OPT,
+        long = "outbound-http-record",
+        conflicts_with = OUTBOUND_HTTP_REPLAY_OPT,
+    )]
+    pub outbound_http_record: Option<PathBuf>,
+    /// Replay the responses recorded to a cassette file, instead of sending the
+    /// outbound HTTP requests of the components.
+    #[structopt(
+        name = OUTBOUND_HTTP_REPLAY_OPT,
+        long = "outbound-http-replay",
+        conflicts_with = OUTBOUND_HTTP_RECORD_OPT,
+    )]
+    pub outbound_http_replay: Option<PathBuf>,
+    /// Request header whose value must match for a recorded response to be
+    /// replayed. Can be given multiple times.
+    #[structopt(
+        name = OUTBOUND_HTTP_MATCH_HEADER_OPT,
+        long = "outbound-http-match-header",
+        requires = OUTBOUND_HTTP_RECORD_OPT,
+        number_of_values = 1,
+    )]
+    pub outbound_http_match_headers: Vec<String>,
 }
 
 impl UpCommand {
@@ -96,18 +202,42 @@ impl UpCommand {
         };
         let working_dir = working_dir_holder.path();
 
-        let mut app = match (&self.app, &self.bindle) {
-            (app, None) => {
+        let mut app = match (&self.app, &self.bindle, &self.oci) {
+            (app, None, None) => {
+                let policy =
+                    SignaturePolicy::new(self.require_signature, self.trusted_keys.as_deref())
+                        .await?;
                 let manifest_file = app
                     .as_deref()
                     .unwrap_or_else(|| DEFAULT_MANIFEST_FILE.as_ref());
-                spin_loader::from_file(manifest_file, working_dir).await?
+                let strategy = match self.copy_files {
+                    true => FilesMountStrategy::Copy,
+                    false => FilesMountStrategy::Direct,
+                };
+                spin_loader::from_file_with_strategy(manifest_file, working_dir, strategy, &policy)
+                    .await?
+            }
+            (None, Some(bindle), None) => {
+                let policy =
+                    SignaturePolicy::new(self.require_signature, self.trusted_keys.as_deref())
+                        .await?;
+                match (&self.bindle_dir, &self.server) {
+                    (Some(dir), _) => {
+                        spin_loader::from_standalone_bindle(bindle, dir, &policy, working_dir)
+                            .await?
+                    }
+                    (None, Some(server)) => {
+                        let connection =
+                            BindleConnectionInfo::new(server, false, self.bindle_auth())?;
+                        spin_loader::from_bindle(bindle, &connection, &policy, working_dir).await?
+                    }
+                    (None, None) => bail!(
+                        "Loading from a bindle requires a Bindle server URL or a standalone bindle directory"
+                    ),
+                }
             }
-            (None, Some(bindle)) => match &self.server {
-                Some(server) => spin_loader::from_bindle(bindle, server, working_dir).await?,
-                _ => bail!("Loading from a bindle requires a Bindle server URL"),
-            },
-            (Some(_), Some(_)) => bail!("Specify only one of app file or bindle ID"),
+            (None, None, Some(reference)) => spin_loader::from_oci(reference, working_dir).await?,
+            _ => bail!("Specify only one of app file, bindle ID or OCI reference"),
         };
         append_env(&mut app, &self.env)?;
 
@@ -128,13 +258,15 @@ impl UpCommand {
             _ => unreachable!(),
         };
 
+        let cassette = self.outbound_http_cassette();
+
         match &app.info.trigger {
             ApplicationTrigger::Http(_) => {
-                let trigger = HttpTrigger::new(self.address, app, tls, self.log).await?;
+                let trigger = HttpTrigger::new(self.address, app, tls, self.log, cassette).await?;
                 trigger.run().await?;
             }
             ApplicationTrigger::Redis(_) => {
-                let trigger = RedisTrigger::new(app, self.log).await?;
+                let trigger = RedisTrigger::new(app, self.log, cassette).await?;
                 trigger.run().await?;
             }
         }
@@ -145,6 +277,25 @@ impl UpCommand {
 
         Ok(())
     }
+
+    fn outbound_http_cassette(&self) -> Option<OutboundHttpCassette> {
+        match (&self.outbound_http_record, &self.outbound_http_replay) {
+            (Some(path), _) => Some(OutboundHttpCassette::Record {
+                path: path.clone(),
+                match_headers: self.outbound_http_match_headers.clone(),
+            }),
+            (None, Some(path)) => Some(OutboundHttpCassette::Replay { path: path.clone() }),
+            (None, None) => None,
+        }
+    }
+
+    fn bindle_auth(&self) -> BindleAuth {
+        BindleAuth {
+            username: self.bindle_username.clone(),
+            password: self.bindle_password.clone(),
+            token: self.bindle_token.clone(),
+        }
+    }
 }
 
 /// Parse the environment variables passed in `key=value` pairs.
@@ -179,3 +330,24 @@ impl WorkingDirectory {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bindle_credentials_in_env_do_not_affect_local_apps() {
+        // A username without a password, and a token with a username, are
+        // only rejected when loading from a Bindle server.
+        std::env::set_var(BINDLE_USERNAME_ENV, "spin");
+        std::env::set_var(BINDLE_TOKEN_ENV, "s3cr3t");
+        std::env::remove_var(BINDLE_PASSWORD_ENV);
+        let up = UpCommand::from_iter_safe(["up", "-f", "spin.toml"]);
+        std::env::remove_var(BINDLE_USERNAME_ENV);
+        std::env::remove_var(BINDLE_TOKEN_ENV);
+
+        let up = up.unwrap();
+        assert_eq!(up.app, Some(PathBuf::from("spin.toml")));
+        assert!(up.bindle_auth().token.is_some());
+    }
+}
@@ -0,0 +1,46 @@
+use anyhow::{bail, Result};
+use spin_loader::local::validation::{validate, Severity};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+const APP_CONFIG_FILE_OPT: &str = "APP_CONFIG_FILE";
+const DEFAULT_MANIFEST_FILE: &str = "spin.toml";
+
+/// Check a Spin application manifest for problems without running it.
+#[derive(StructOpt, Debug)]
+#[structopt(about = "Check the Spin application manifest for problems")]
+pub struct ValidateCommand {
+    /// Path to spin.toml.
+    #[structopt(
+        name = APP_CONFIG_FILE_OPT,
+        short = "f",
+        long = "file",
+    )]
+    pub app: Option<PathBuf>,
+}
+
+impl ValidateCommand {
+    pub async fn run(self) -> Result<()> {
+        let manifest_file = self
+            .app
+            .as_deref()
+            .unwrap_or_else(|| DEFAULT_MANIFEST_FILE.as_ref());
+
+        let diagnostics = validate(manifest_file).await?;
+        for d in &diagnostics {
+            println!("{}", d);
+        }
+
+        let errors = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+        match errors {
+            0 => {
+                println!("{} is valid", manifest_file.display());
+                Ok(())
+            }
+            n => bail!("{} has {} error(s)", manifest_file.display(), n),
+        }
+    }
+}